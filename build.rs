@@ -0,0 +1,26 @@
+// Bakes the ROM named by CHIP8_EMBED_ROM into the binary, which then runs it
+// when started without a ROM argument. Without the variable an empty file is
+// embedded and the binary behaves as usual.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=CHIP8_EMBED_ROM");
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("embedded.rom");
+
+    let (data, name) = match env::var_os("CHIP8_EMBED_ROM") {
+        Some(path) => {
+            let path = Path::new(&path);
+            println!("cargo:rerun-if-changed={}", path.display());
+            let data = fs::read(path).unwrap_or_else(|e| {
+                panic!("CHIP8_EMBED_ROM: cannot read {}: {}", path.display(), e)
+            });
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (data, name)
+        }
+        None => (Vec::new(), String::new()),
+    };
+    fs::write(&out, data).unwrap();
+    println!("cargo:rustc-env=CHIP8_EMBEDDED_ROM_NAME={}", name);
+}
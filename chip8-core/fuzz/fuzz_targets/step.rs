@@ -0,0 +1,8 @@
+#![no_main]
+use chip8_core::{fuzz_step, Chip8State};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u16, Chip8State)| {
+    let (opcode, state) = input;
+    fuzz_step(opcode, &state);
+});
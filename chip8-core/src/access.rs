@@ -0,0 +1,59 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// How often every memory address was read, written and executed since the
+/// machine was built, one entry per byte of memory. Only kept when enabled
+/// with [`Chip8Builder::count_accesses`](crate::Chip8Builder::count_accesses).
+///
+/// Reads and writes are the ones made through I (DXYN, FX33, FX55 and FX65),
+/// executing an instruction counts both of its bytes. Access from outside
+/// through [`Chip8::memory_mut`](crate::Chip8::memory_mut) is not counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessCounts {
+    pub reads: Vec<u32>,
+    pub writes: Vec<u32>,
+    pub executes: Vec<u32>,
+    /// The writes to bytes that had been executed before, i.e. the program
+    /// rewriting its own code. Addresses with a count here are not safe to
+    /// decode ahead of time.
+    pub code_writes: Vec<u32>,
+}
+
+/// A write to memory that had already been executed as code, see
+/// [`Chip8::take_code_writes`](crate::Chip8::take_code_writes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeWrite {
+    /// The instruction doing the write
+    pub pc: usize,
+    pub addr: usize,
+    pub value: u8,
+}
+
+impl AccessCounts {
+    pub(crate) fn new(len: usize) -> AccessCounts {
+        AccessCounts {
+            reads: vec![0; len],
+            writes: vec![0; len],
+            executes: vec![0; len],
+            code_writes: vec![0; len],
+        }
+    }
+
+    pub(crate) fn read(&mut self, addr: usize) {
+        self.reads[addr] = self.reads[addr].saturating_add(1);
+    }
+
+    // true when the write lands on code
+    pub(crate) fn write(&mut self, addr: usize) -> bool {
+        self.writes[addr] = self.writes[addr].saturating_add(1);
+        if self.executes[addr] == 0 {
+            return false;
+        }
+        self.code_writes[addr] = self.code_writes[addr].saturating_add(1);
+        true
+    }
+
+    pub(crate) fn execute(&mut self, addr: usize) {
+        self.executes[addr] = self.executes[addr].saturating_add(1);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::chip8::{Chip8, HEIGHT, WIDTH};
+use crate::error::Chip8Error;
+
+/// The screen packed one bit per pixel, row major, most significant bit
+/// first.
+pub type PackedScreen = [u8; WIDTH * HEIGHT / 8];
+
+/// A machine driven a 60Hz frame at a time, for game playing bots and
+/// reinforcement learning experiments: set the keys, advance some frames,
+/// look at the screen and memory, repeat.
+///
+/// ```
+/// use chip8_core::{Agent, Chip8};
+///
+/// let mut c8 = Chip8::builder().seed(0).build();
+/// c8.load_rom(&[0x12, 0x00]).unwrap(); // loop forever
+/// let mut agent = Agent::new(c8, 10);
+/// agent.set_keys(1 << 0x5);
+/// agent.advance(3).unwrap();
+/// assert_eq!(agent.frames(), 3);
+/// assert!(agent.machine().keypad()[0x5]);
+/// ```
+pub struct Agent {
+    c8: Chip8,
+    speed: u32,
+    frames: u64,
+}
+
+impl Agent {
+    /// Drive `c8`, running `speed` instructions per frame.
+    pub fn new(c8: Chip8, speed: u32) -> Agent {
+        Agent {
+            c8,
+            speed,
+            frames: 0,
+        }
+    }
+
+    /// Hold exactly the keys in `keys`, see [`Chip8::set_keys`].
+    pub fn set_keys(&mut self, keys: u16) {
+        self.c8.set_keys(keys);
+    }
+
+    /// The keys held down, as for `set_keys`.
+    pub fn keys(&self) -> u16 {
+        self.c8.keys()
+    }
+
+    /// Run `frames` frames, stopping early if the program crashes.
+    pub fn advance(&mut self, frames: u32) -> Result<(), Chip8Error> {
+        for _ in 0..frames {
+            for _ in 0..self.speed {
+                self.c8.tick_cpu()?;
+            }
+            self.c8.tick_60hz();
+            self.frames += 1;
+        }
+        Ok(())
+    }
+
+    /// Frames run since the agent was created.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn screen(&self) -> PackedScreen {
+        let mut packed = [0; WIDTH * HEIGHT / 8];
+        for (i, &pixel) in self.c8.pixel_buffer().iter().flatten().enumerate() {
+            packed[i / 8] |= (pixel as u8) << (7 - i % 8);
+        }
+        packed
+    }
+
+    pub fn machine(&self) -> &Chip8 {
+        &self.c8
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Chip8 {
+        &mut self.c8
+    }
+}
@@ -0,0 +1,149 @@
+use crate::chip8::{
+    Chip8, DEFAULT_MEMORY_SIZE, DEFAULT_STACK_DEPTH, MAX_MEMORY_SIZE, MAX_STACK_DEPTH,
+    MIN_MEMORY_SIZE,
+};
+use crate::error::{MemoryPolicy, WriteProtection};
+use crate::font::Font;
+use crate::quirks::Quirks;
+
+/// Configures a [`Chip8`] before it is created.
+///
+/// ```
+/// use chip8_core::{Chip8, Quirks};
+///
+/// let quirks: Quirks = "shift-vy,clip".parse().unwrap();
+/// let c8 = Chip8::builder().quirks(quirks).build();
+/// assert_eq!(c8.quirks(), quirks);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Chip8Builder {
+    pub(crate) quirks: Quirks,
+    pub(crate) memory_policy: MemoryPolicy,
+    pub(crate) write_protection: WriteProtection,
+    pub(crate) stack_depth: usize,
+    pub(crate) memory_size: usize,
+    pub(crate) seed: Option<u64>,
+    pub(crate) count_accesses: bool,
+    pub(crate) font: Font,
+    pub(crate) debug_port: Option<usize>,
+    pub(crate) aligned_pc: bool,
+}
+
+impl Default for Chip8Builder {
+    fn default() -> Self {
+        Chip8Builder {
+            quirks: Quirks::default(),
+            memory_policy: MemoryPolicy::default(),
+            write_protection: WriteProtection::default(),
+            stack_depth: DEFAULT_STACK_DEPTH,
+            memory_size: DEFAULT_MEMORY_SIZE,
+            seed: None,
+            count_accesses: false,
+            font: Font::default(),
+            debug_port: None,
+            aligned_pc: false,
+        }
+    }
+}
+
+impl Chip8Builder {
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// How to handle reads and writes through I past the end of memory.
+    pub fn memory_policy(mut self, policy: MemoryPolicy) -> Self {
+        self.memory_policy = policy;
+        self
+    }
+
+    /// Guard the memory below 0x200 against writes through I, off by
+    /// default. Plugins mapped there are still written to.
+    pub fn write_protection(mut self, protection: WriteProtection) -> Self {
+        self.write_protection = protection;
+        self
+    }
+
+    /// Maximum number of nested subroutine calls, 16 by default. Calling
+    /// deeper than this stops with a stack overflow error. See
+    /// [`Platform::stack_depth`](crate::Platform::stack_depth) for what a
+    /// platform allowed.
+    ///
+    /// # Panics
+    ///
+    /// When `depth` is 0 or more than [`MAX_STACK_DEPTH`].
+    pub fn stack_depth(mut self, depth: usize) -> Self {
+        assert!(
+            (1..=MAX_STACK_DEPTH).contains(&depth),
+            "stack depth {} is outside 1..={}",
+            depth,
+            MAX_STACK_DEPTH
+        );
+        self.stack_depth = depth;
+        self
+    }
+
+    /// Bytes of memory, 4096 by default. See
+    /// [`Platform::memory_size`](crate::Platform::memory_size) for what a
+    /// platform had.
+    ///
+    /// # Panics
+    ///
+    /// When `size` is not between [`MIN_MEMORY_SIZE`] and [`MAX_MEMORY_SIZE`].
+    pub fn memory_size(mut self, size: usize) -> Self {
+        assert!(
+            (MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&size),
+            "memory size {:#x} is outside {:#x}..={:#x}",
+            size,
+            MIN_MEMORY_SIZE,
+            MAX_MEMORY_SIZE
+        );
+        self.memory_size = size;
+        self
+    }
+
+    /// Seed the random number generator used by CXNN so runs can be
+    /// reproduced. A random seed is picked when this is not set (or `0`
+    /// without the `std` feature).
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The digits FX29 points at, see [`Platform::font`](crate::Platform::font)
+    /// for the one a platform used.
+    pub fn font(mut self, font: Font) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Count the reads, writes and executions of every memory address, see
+    /// [`Chip8::accesses`]. Off by default since it costs a little on every
+    /// instruction.
+    pub fn count_accesses(mut self, count: bool) -> Self {
+        self.count_accesses = count;
+        self
+    }
+
+    /// Collect every byte a program stores at this address (with FX33 or
+    /// FX55) so the frontend can print it, see [`Chip8::take_debug_output`].
+    /// The write still reaches memory. Off (`None`) by default, since games
+    /// may well use any address for their own data.
+    pub fn debug_port(mut self, addr: Option<usize>) -> Self {
+        self.debug_port = addr;
+        self
+    }
+
+    /// Stop with [`Chip8Error::PcMisaligned`](crate::Chip8Error::PcMisaligned)
+    /// when the pc lands on an odd address. Off by default: jumping to an odd
+    /// address is legal, and a few programs do it on purpose.
+    pub fn aligned_pc(mut self, aligned: bool) -> Self {
+        self.aligned_pc = aligned;
+        self
+    }
+
+    pub fn build(self) -> Chip8 {
+        Chip8::with_config(self)
+    }
+}
@@ -0,0 +1,729 @@
+use crate::access::{AccessCounts, CodeWrite};
+use crate::builder::Chip8Builder;
+use crate::error::{Chip8Error, LoadError, MemoryPolicy, WriteProtection};
+use crate::font::Font;
+use crate::instruction::{decode, Instruction, Opcode};
+use crate::plugin::Plugin;
+use crate::quirks::Quirks;
+use crate::rng::Rng;
+use crate::rom_id::RomId;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use rand::SeedableRng;
+
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+/// The smallest memory a machine can be built with, room for the interpreter
+/// area and one instruction.
+pub const MIN_MEMORY_SIZE: usize = 0x202;
+/// The largest, XO-CHIP's 64K. Save states store addresses as 16 bits.
+pub const MAX_MEMORY_SIZE: usize = 0x10000;
+/// What a machine gets unless its builder says otherwise, the VIP's 4K.
+pub const DEFAULT_MEMORY_SIZE: usize = 4096;
+/// The call stack a machine gets unless its builder says otherwise, the 16
+/// levels most interpreters since the HP48 have.
+pub const DEFAULT_STACK_DEPTH: usize = 16;
+/// Instructions per 60Hz frame hosts run unless told otherwise, 600 a second.
+pub const DEFAULT_SPEED: u32 = 10;
+/// The deepest call stack a machine can be built with, far more than any
+/// real interpreter had room for.
+pub const MAX_STACK_DEPTH: usize = 1024;
+
+// number of executed instructions kept for crash reports
+const HISTORY_LEN: usize = 16;
+// programs are loaded here, everything below belongs to the interpreter
+const PROGRAM_START: usize = 0x200;
+
+pub struct Chip8 {
+    pub(crate) memory: Vec<u8>,
+    pub(crate) registers: Vec<u8>,
+    pub(crate) index: usize, // index register
+    pub(crate) pc: usize,    // program counter
+    pub(crate) pixel_buffer: Vec<Vec<bool>>,
+    pub(crate) delay_timer: u8,
+    pub(crate) sound_timer: u8,
+
+    pub(crate) call_stack: Vec<usize>,
+    pub(crate) stack_depth: usize,
+    pub(crate) keypad: Vec<bool>,
+
+    // set whenever the pixel buffer changes, cleared by the frontend
+    pub(crate) draw_flag: bool,
+
+    // (pc, opcode) of the last few instructions run by `tick_cpu`, oldest first
+    history: VecDeque<(usize, Opcode)>,
+
+    quirks: Quirks,
+    memory_policy: MemoryPolicy,
+    write_protection: WriteProtection,
+    pub(crate) seed: u64,
+    pub(crate) rom: Option<RomId>,
+    pub(crate) rng: Rng,
+    accesses: Option<AccessCounts>,
+    font: Font,
+    debug_port: Option<usize>,
+    aligned_pc: bool,
+    debug_output: Vec<u8>,
+    code_writes: Vec<CodeWrite>,
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+/// A fresh seed for CXNN, drawn from the OS entropy source.
+#[cfg(feature = "std")]
+pub fn random_seed() -> u64 {
+    use rand::Rng as _;
+    rand::rngs::SmallRng::from_entropy().gen()
+}
+
+// there is no entropy source to draw from without std
+#[cfg(not(feature = "std"))]
+pub(crate) fn random_seed() -> u64 {
+    0
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chip8 {
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> Chip8Builder {
+        Chip8Builder::default()
+    }
+
+    pub(crate) fn with_config(config: Chip8Builder) -> Self {
+        let seed = config.seed.unwrap_or_else(random_seed);
+        let memory_len = config.memory_size;
+        let mut c8 = Chip8 {
+            memory: vec![0; memory_len],
+            registers: vec![0; 16], // 16 8-bit registers
+            index: 0,
+            pc: PROGRAM_START, // program counter starts at 0x200
+            pixel_buffer: vec![vec![false; WIDTH]; HEIGHT], // 2048 pixels
+            delay_timer: 0,
+            sound_timer: 0,
+
+            call_stack: Vec::with_capacity(config.stack_depth),
+            stack_depth: config.stack_depth,
+            keypad: vec![false; 16],
+            draw_flag: false,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            quirks: config.quirks,
+            memory_policy: config.memory_policy,
+            write_protection: config.write_protection,
+            seed,
+            rom: None,
+            rng: Rng::seed_from_u64(seed),
+            accesses: config.count_accesses.then(|| AccessCounts::new(memory_len)),
+            font: config.font,
+            debug_port: config.debug_port,
+            aligned_pc: config.aligned_pc,
+            debug_output: Vec::new(),
+            code_writes: Vec::new(),
+            plugins: Vec::new(),
+        };
+
+        c8.load_font();
+        c8
+    }
+
+    fn load_font(&mut self) {
+        let start = self.font.address();
+        self.memory[start..start + 80].copy_from_slice(self.font.data());
+    }
+
+    /// Copy a program into memory at 0x200.
+    pub fn load_rom(&mut self, data: &[u8]) -> Result<(), LoadError> {
+        Chip8::check_rom(data, self.memory.len())?;
+        self.memory[PROGRAM_START..(PROGRAM_START + data.len())].copy_from_slice(data);
+        self.rom = Some(RomId::of(data));
+        Ok(())
+    }
+
+    /// Identifies the ROM last passed to `load_rom`.
+    pub fn rom_id(&self) -> Option<&RomId> {
+        self.rom.as_ref()
+    }
+
+    /// The largest ROM `load_rom` accepts.
+    pub fn max_rom_size(&self) -> usize {
+        self.memory.len() - PROGRAM_START
+    }
+
+    /// Whether `load_rom` would accept `data` on a machine with
+    /// `memory_size` bytes of memory, without building one.
+    pub fn check_rom(data: &[u8], memory_size: usize) -> Result<(), LoadError> {
+        let max = memory_size.saturating_sub(PROGRAM_START);
+        if data.is_empty() {
+            return Err(LoadError::Empty);
+        }
+        if data.len() > max {
+            return Err(LoadError::TooLarge {
+                size: data.len(),
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    /// Advance the CPU clock: fetch, decode and execute a single
+    /// instruction. The core never sleeps or looks at the time, so a host
+    /// drives it entirely with this and [`tick_60hz`](Chip8::tick_60hz), at
+    /// whatever ratio and pace it likes.
+    pub fn tick_cpu(&mut self) -> Result<(), Chip8Error> {
+        if self.pc < PROGRAM_START {
+            return Err(Chip8Error::PcInInterpreterArea { pc: self.pc });
+        }
+        if self.aligned_pc && !self.pc.is_multiple_of(2) {
+            return Err(Chip8Error::PcMisaligned { pc: self.pc });
+        }
+        if self.pc + 1 >= self.memory.len() {
+            return Err(Chip8Error::PcOutOfRange { pc: self.pc });
+        }
+        let oc = self.fetch();
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back((self.pc, oc));
+        if let Some(accesses) = self.accesses.as_mut() {
+            accesses.execute(self.pc);
+            accesses.execute(self.pc + 1);
+        }
+        log::trace!("{:#05x}  {:04x}  {:?}", self.pc, oc, decode(oc));
+        self.exec_opcode(oc)?;
+        #[cfg(feature = "debug-invariants")]
+        self.check_invariants();
+        Ok(())
+    }
+
+    // Panics if the machine is in a state no well-formed program should be
+    // able to reach. The timers are u8, so they cannot exceed 255. The pc and
+    // I may both legally point anywhere until they are used, see tick_cpu and
+    // index_addr.
+    #[cfg(feature = "debug-invariants")]
+    fn check_invariants(&self) {
+        assert!(
+            self.call_stack.len() <= self.stack_depth,
+            "call stack depth {} exceeds the limit of {}",
+            self.call_stack.len(),
+            self.stack_depth
+        );
+        assert_eq!(self.registers.len(), 16, "register file was resized");
+        assert_eq!(self.keypad.len(), 16, "keypad was resized");
+        assert!(
+            self.pixel_buffer.len() == HEIGHT
+                && self.pixel_buffer.iter().all(|row| row.len() == WIDTH),
+            "pixel buffer is not {}x{}",
+            WIDTH,
+            HEIGHT
+        );
+    }
+
+    /// Decode and execute `oc` as if it had been fetched at the current pc.
+    pub fn exec_opcode(&mut self, oc: Opcode) -> Result<(), Chip8Error> {
+        let instruction = decode(oc);
+        if instruction == Instruction::Noop && !self.plugins.is_empty() {
+            // the plugin gets the whole machine, so it is lent out of it
+            let mut plugins = core::mem::take(&mut self.plugins);
+            let result = plugins
+                .iter_mut()
+                .find(|plugin| plugin.claims_opcode(oc))
+                .map(|plugin| {
+                    self.pc += 2;
+                    plugin.execute(oc, self)
+                });
+            self.plugins = plugins;
+            if let Some(result) = result {
+                return result;
+            }
+        }
+        self.execute(instruction)
+    }
+
+    /// Extend the interpreter with `plugin`, see [`Plugin`]. Plugins are asked
+    /// in the order they were added.
+    pub fn add_plugin(&mut self, plugin: Box<dyn Plugin>) {
+        self.plugins.push(plugin);
+    }
+
+    pub fn fetch(&self) -> Opcode {
+        (self.memory[self.pc] as u16) << 8 | (self.memory[self.pc + 1] as u16)
+    }
+
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        let pc = self.pc;
+        match instruction {
+            Instruction::ClearScreen => {
+                self.clear_screen();
+                self.pc += 2;
+            }
+            Instruction::Return => {
+                let ret_addr = self
+                    .call_stack
+                    .pop()
+                    .ok_or(Chip8Error::StackUnderflow { pc })?;
+                self.pc = ret_addr;
+            }
+            Instruction::JumpTo(addr) => self.pc = addr,
+            Instruction::Subroutine(addr) => {
+                if self.call_stack.len() >= self.stack_depth {
+                    return Err(Chip8Error::StackOverflow { pc });
+                }
+                self.call_stack.push(self.pc + 2);
+                self.pc = addr;
+            }
+            Instruction::SkipIfRegisterEqualValue(reg, val) => {
+                self.pc += 2;
+                if self.registers[reg] == val {
+                    self.pc += 2;
+                }
+            }
+            Instruction::SkipIfRegisterNotEqualValue(reg, val) => {
+                self.pc += 2;
+                if self.registers[reg] != val {
+                    self.pc += 2;
+                }
+            }
+            Instruction::SkipIfRegisterEqualRegister(reg1, reg2) => {
+                self.pc += 2;
+                if self.registers[reg1] == self.registers[reg2] {
+                    self.pc += 2;
+                }
+            }
+            Instruction::SetRegisterToValue(reg, val) => {
+                self.pc += 2;
+                self.registers[reg] = val;
+            }
+            Instruction::AddRegisterValue(reg, val) => {
+                self.pc += 2;
+                self.registers[reg] = self.registers[reg].wrapping_add(val);
+            }
+            Instruction::SetRegister(reg1, reg2) => {
+                self.pc += 2;
+                self.registers[reg1] = self.registers[reg2];
+            }
+            Instruction::SetRegisterOR(reg1, reg2) => {
+                self.pc += 2;
+                self.registers[reg1] |= self.registers[reg2];
+                if self.quirks.logic_resets_vf {
+                    self.registers[15] = 0;
+                }
+            }
+            Instruction::SetRegisterAND(reg1, reg2) => {
+                self.pc += 2;
+                self.registers[reg1] &= self.registers[reg2];
+                if self.quirks.logic_resets_vf {
+                    self.registers[15] = 0;
+                }
+            }
+            Instruction::SetRegisterXOR(reg1, reg2) => {
+                self.pc += 2;
+                self.registers[reg1] ^= self.registers[reg2];
+                if self.quirks.logic_resets_vf {
+                    self.registers[15] = 0;
+                }
+            }
+            Instruction::AddRegisterToRegister(reg1, reg2) => {
+                self.pc += 2;
+                let (res, overflow) = self.registers[reg1].overflowing_add(self.registers[reg2]);
+                self.registers[reg1] = res;
+                self.registers[0xF] = if overflow { 1 } else { 0 };
+            }
+            Instruction::SubRegisterToRegister85(reg1, reg2) => {
+                self.pc += 2;
+                let vx = self.registers[reg1];
+                let vy = self.registers[reg2];
+
+                // VF is written last so it wins when it is also the destination
+                self.registers[reg1] = vx.wrapping_sub(vy);
+                self.registers[15] = if vx >= vy { 1 } else { 0 }; // borrow does not occur
+            }
+            Instruction::SubRegisterToRegister87(reg1, reg2) => {
+                self.pc += 2;
+                let vx = self.registers[reg1];
+                let vy = self.registers[reg2];
+
+                self.registers[reg1] = vy.wrapping_sub(vx);
+                self.registers[15] = if vx <= vy { 1 } else { 0 }; // borrow does not occur
+            }
+            Instruction::ShiftRight(reg1, reg2) => {
+                self.pc += 2;
+                let src = if self.quirks.shift_uses_vy {
+                    reg2
+                } else {
+                    reg1
+                };
+                let vx = self.registers[src];
+                self.registers[reg1] = vx >> 1;
+                self.registers[15] = vx & 1;
+            }
+            Instruction::ShiftLeft(reg1, reg2) => {
+                self.pc += 2;
+                let src = if self.quirks.shift_uses_vy {
+                    reg2
+                } else {
+                    reg1
+                };
+                let vx = self.registers[src];
+                self.registers[reg1] = vx << 1;
+                self.registers[15] = vx >> 7;
+            }
+            Instruction::SkipIfRegisterNotEqualRegister(reg1, reg2) => {
+                self.pc += 2;
+                if self.registers[reg1] != self.registers[reg2] {
+                    self.pc += 2;
+                }
+            }
+            Instruction::SetIndex(addr) => {
+                self.index = addr;
+                self.pc += 2;
+            }
+            Instruction::JumpRelV0(val) => {
+                let reg = if self.quirks.jump_uses_vx {
+                    (val >> 8) & 0xF
+                } else {
+                    0
+                };
+                self.pc = val.wrapping_add(self.registers[reg] as usize);
+            }
+            Instruction::RandomAND(reg, val) => {
+                self.pc += 2;
+                let random_byte = self.rng.next_byte();
+                self.registers[reg] = random_byte & val;
+            }
+            Instruction::Draw(reg1, reg2, height) => {
+                self.pc += 2;
+                let x = self.registers[reg1] as usize;
+                let y = self.registers[reg2] as usize;
+                self.registers[15] = 0;
+
+                let mut did_overflow: bool = false;
+
+                for i in 0usize..(height as usize) {
+                    let word = self.load_byte(pc, i)?;
+                    for j in 0usize..8 {
+                        if self.quirks.clip_sprites
+                            && ((x % WIDTH) + j >= WIDTH || (y % HEIGHT) + i >= HEIGHT)
+                        {
+                            continue;
+                        }
+                        let tx = (x + j) % WIDTH;
+                        let ty = (y + i) % HEIGHT;
+                        if word & (0x80 >> j) != 0 {
+                            if self.pixel_buffer[ty][tx] {
+                                did_overflow = true;
+                            }
+                            self.pixel_buffer[ty][tx] = !self.pixel_buffer[ty][tx];
+                        }
+                    }
+                }
+
+                self.registers[15] = if did_overflow { 1 } else { 0 };
+                self.draw_flag = true;
+            }
+            // only the low nibble of VX names a key, as on the VIP
+            Instruction::SkipIfKey(reg) => {
+                self.pc += 2;
+                if self.keypad[(self.registers[reg] & 0xF) as usize] {
+                    self.pc += 2;
+                }
+            }
+            Instruction::SkipIfNotKey(reg) => {
+                self.pc += 2;
+                if !self.keypad[(self.registers[reg] & 0xF) as usize] {
+                    self.pc += 2;
+                }
+            }
+            Instruction::SetToDelayTimer(reg) => {
+                self.pc += 2;
+                self.registers[reg] = self.delay_timer;
+            }
+            Instruction::GetKeyPress(reg) => {
+                for i in 0..16 {
+                    if self.keypad[i] {
+                        self.pc += 2;
+                        self.registers[reg] = i as u8;
+                        return Ok(());
+                    }
+                }
+            }
+            Instruction::SetDelayTimer(reg) => {
+                self.pc += 2;
+                self.delay_timer = self.registers[reg];
+            }
+            Instruction::SetSoundTimer(reg) => {
+                self.pc += 2;
+                self.sound_timer = self.registers[reg];
+            }
+            Instruction::AddToIndexRegister(reg) => {
+                self.pc += 2;
+                self.index += self.registers[reg] as usize;
+                self.registers[15] = if self.index > 0x0FFF { 1 } else { 0 };
+            }
+            Instruction::SetIndexToSpriteAddr(reg) => {
+                self.pc += 2;
+                let vx = self.registers[reg];
+                self.index = self.font.address() + 5 * vx as usize;
+            }
+            Instruction::Bcd(reg) => {
+                self.pc += 2;
+                let vx = self.registers[reg];
+                let digits = [vx / 100, (vx / 10) % 10, vx % 10];
+                for (i, &digit) in digits.iter().enumerate() {
+                    self.store(pc, i, digit)?;
+                }
+            }
+            Instruction::DumpRegistersTill(reg) => {
+                self.pc += 2;
+                for i in 0..=(reg as u8) {
+                    self.store(pc, i as usize, self.registers[i as usize])?;
+                }
+                if self.quirks.load_store_increments_i {
+                    self.index += reg + 1;
+                }
+            }
+            Instruction::LoadRegistersTill(reg) => {
+                self.pc += 2;
+                for i in 0..=(reg as u8) {
+                    self.registers[i as usize] = self.load_byte(pc, i as usize)?;
+                }
+                if self.quirks.load_store_increments_i {
+                    self.index += reg + 1;
+                }
+            }
+
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // address of the byte `offset` past I, with the memory policy applied
+    fn index_addr(&self, pc: usize, offset: usize) -> Result<usize, Chip8Error> {
+        let addr = self.index + offset;
+        let len = self.memory.len();
+        if addr < len {
+            return Ok(addr);
+        }
+        let resolved = match self.memory_policy {
+            MemoryPolicy::Wrap => addr % len,
+            MemoryPolicy::Clamp => len - 1,
+            MemoryPolicy::Error => return Err(Chip8Error::MemoryOutOfRange { pc, addr }),
+        };
+        #[cfg(feature = "debug-invariants")]
+        assert!(
+            resolved < len,
+            "memory policy {} sent I + {} = {:#x} to {:#x}, outside memory",
+            self.memory_policy,
+            offset,
+            addr,
+            resolved
+        );
+        Ok(resolved)
+    }
+
+    // read through I, counted when accesses are tracked
+    fn load_byte(&mut self, pc: usize, offset: usize) -> Result<u8, Chip8Error> {
+        let addr = self.index_addr(pc, offset)?;
+        if let Some(accesses) = self.accesses.as_mut() {
+            accesses.read(addr);
+        }
+        match self.plugin_at(addr) {
+            Some(plugin) => Ok(plugin.read(addr)),
+            None => Ok(self.memory[addr]),
+        }
+    }
+
+    fn plugin_at(&mut self, addr: usize) -> Option<&mut Box<dyn Plugin>> {
+        self.plugins
+            .iter_mut()
+            .find(|plugin| plugin.region().is_some_and(|region| region.contains(&addr)))
+    }
+
+    // write through I, counted the same way and copied to the debug port
+    fn store(&mut self, pc: usize, offset: usize, value: u8) -> Result<(), Chip8Error> {
+        let addr = self.index_addr(pc, offset)?;
+        if let Some(accesses) = self.accesses.as_mut() {
+            if accesses.write(addr) {
+                self.code_writes.push(CodeWrite { pc, addr, value });
+            }
+        }
+        if self.debug_port == Some(addr) {
+            self.debug_output.push(value);
+        }
+        if let Some(plugin) = self.plugin_at(addr) {
+            plugin.write(addr, value);
+            return Ok(());
+        }
+        if addr < PROGRAM_START {
+            match self.write_protection {
+                WriteProtection::Off => {}
+                WriteProtection::Warn => {
+                    log::warn!(
+                        "instruction at {:#05x} wrote {:#04x} to {:#05x}, below 0x200, ignoring it",
+                        pc,
+                        value,
+                        addr
+                    );
+                    return Ok(());
+                }
+                WriteProtection::Strict => return Err(Chip8Error::ProtectedWrite { pc, addr }),
+            }
+        }
+        self.memory[addr] = value;
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) {
+        self.pixel_buffer = vec![vec![false; WIDTH]; HEIGHT];
+        self.draw_flag = true;
+    }
+
+    /// Advance the 60Hz clock, counting the delay and sound timers down.
+    pub fn tick_60hz(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+    }
+
+    /// Press or release keypad key `key`, only its low nibble is used.
+    pub fn set_key(&mut self, key: usize, pressed: bool) {
+        self.keypad[key & 0xF] = pressed;
+    }
+
+    /// Which of the 16 keypad keys are held down.
+    pub fn keypad(&self) -> &[bool] {
+        &self.keypad
+    }
+
+    /// Hold exactly the keys in `keys`, bit n being keypad key n.
+    pub fn set_keys(&mut self, keys: u16) {
+        for (key, held) in self.keypad.iter_mut().enumerate() {
+            *held = keys & (1 << key) != 0;
+        }
+    }
+
+    /// The keys held down, as for `set_keys`.
+    pub fn keys(&self) -> u16 {
+        self.keypad
+            .iter()
+            .enumerate()
+            .fold(0, |keys, (key, &held)| keys | (held as u16) << key)
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn font(&self) -> Font {
+        self.font
+    }
+
+    pub fn memory_policy(&self) -> MemoryPolicy {
+        self.memory_policy
+    }
+
+    pub fn write_protection(&self) -> WriteProtection {
+        self.write_protection
+    }
+
+    pub fn aligned_pc(&self) -> bool {
+        self.aligned_pc
+    }
+
+    pub fn stack_depth(&self) -> usize {
+        self.stack_depth
+    }
+
+    /// The seed the CXNN random number generator started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The pc and opcode of the last few instructions executed by `tick_cpu`,
+    /// oldest first, for reporting where things went wrong.
+    pub fn recent_instructions(&self) -> impl Iterator<Item = (usize, Opcode)> + '_ {
+        self.history.iter().copied()
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn registers(&self) -> &[u8] {
+        &self.registers
+    }
+
+    pub fn registers_mut(&mut self) -> &mut [u8] {
+        &mut self.registers
+    }
+
+    pub fn pixel_buffer(&self) -> &[Vec<bool>] {
+        &self.pixel_buffer
+    }
+
+    /// FNV-1a hash of the pixel buffer, a cheap way to compare screens.
+    pub fn framebuffer_hash(&self) -> u64 {
+        self.pixel_buffer
+            .iter()
+            .flatten()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, &pixel| {
+                (hash ^ pixel as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// Returns true once after every change to the pixel buffer.
+    pub fn take_draw_flag(&mut self) -> bool {
+        let flag = self.draw_flag;
+        self.draw_flag = false;
+        flag
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// Per address access counts, when they were enabled with
+    /// [`Chip8Builder::count_accesses`].
+    pub fn accesses(&self) -> Option<&AccessCounts> {
+        self.accesses.as_ref()
+    }
+
+    /// The writes to already executed code since the last call. Only tracked
+    /// along with [`Chip8::accesses`], which keeps the totals per address.
+    pub fn take_code_writes(&mut self) -> Vec<CodeWrite> {
+        core::mem::take(&mut self.code_writes)
+    }
+
+    /// The bytes written to the debug port since the last call, see
+    /// [`Chip8Builder::debug_port`].
+    pub fn take_debug_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.debug_output)
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+}
@@ -0,0 +1,190 @@
+use core::fmt;
+use core::str::FromStr;
+
+/// Raised by [`Chip8::tick_cpu`](crate::Chip8::tick_cpu) when the program does
+/// something the interpreter cannot carry out. `pc` is the address of the
+/// offending instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// An instruction addressed memory past the end through the index
+    /// register, with the memory policy set to `Error`.
+    MemoryOutOfRange { pc: usize, addr: usize },
+    /// 2NNN with the call stack already at its configured depth.
+    StackOverflow { pc: usize },
+    /// 00EE with nothing on the call stack.
+    StackUnderflow { pc: usize },
+    /// The pc points past the end of memory, where no instruction can be
+    /// fetched.
+    PcOutOfRange { pc: usize },
+    /// The pc points below 0x200, into memory reserved for the interpreter.
+    PcInInterpreterArea { pc: usize },
+    /// The pc points at an odd address, with aligned pcs required.
+    PcMisaligned { pc: usize },
+    /// An instruction wrote below 0x200, with write protection set to
+    /// `Strict`.
+    ProtectedWrite { pc: usize, addr: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Chip8Error::MemoryOutOfRange { pc, addr } => write!(
+                f,
+                "instruction at {:#05x} accessed memory at {:#x} through I, past the end of memory",
+                pc, addr
+            ),
+            Chip8Error::StackOverflow { pc } => {
+                write!(f, "call at {:#05x} overflowed the call stack", pc)
+            }
+            Chip8Error::StackUnderflow { pc } => {
+                write!(f, "return at {:#05x} with an empty call stack", pc)
+            }
+            Chip8Error::PcOutOfRange { pc } => {
+                write!(f, "pc {:#05x} is past the end of memory", pc)
+            }
+            Chip8Error::PcInInterpreterArea { pc } => {
+                write!(f, "pc {:#05x} is inside the interpreter area", pc)
+            }
+            Chip8Error::PcMisaligned { pc } => write!(f, "pc {:#05x} is not aligned", pc),
+            Chip8Error::ProtectedWrite { pc, addr } => write!(
+                f,
+                "instruction at {:#05x} wrote to {:#05x}, inside the protected interpreter area",
+                pc, addr
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Chip8Error {}
+
+/// Returned by [`Chip8::load_rom`](crate::Chip8::load_rom) for ROMs that cannot
+/// be run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    Empty,
+    /// The ROM does not fit in the memory above 0x200.
+    TooLarge {
+        size: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Empty => write!(f, "the ROM is empty"),
+            LoadError::TooLarge { size, max } => write!(
+                f,
+                "the ROM is {} bytes, at most {} bytes fit in memory",
+                size, max
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LoadError {}
+
+/// What to do when an instruction reads or writes memory through the index
+/// register past the end of memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum MemoryPolicy {
+    /// Wrap the address around to the start of memory.
+    #[default]
+    Wrap,
+    /// Use the last byte of memory instead.
+    Clamp,
+    /// Stop with [`Chip8Error::MemoryOutOfRange`].
+    Error,
+}
+
+/// Parses `wrap`, `clamp` or `error`.
+impl FromStr for MemoryPolicy {
+    type Err = UnknownMemoryPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(MemoryPolicy::Wrap),
+            "clamp" => Ok(MemoryPolicy::Clamp),
+            "error" => Ok(MemoryPolicy::Error),
+            _ => Err(UnknownMemoryPolicy),
+        }
+    }
+}
+
+impl fmt::Display for MemoryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MemoryPolicy::Wrap => "wrap",
+            MemoryPolicy::Clamp => "clamp",
+            MemoryPolicy::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownMemoryPolicy;
+
+impl fmt::Display for UnknownMemoryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected one of wrap, clamp, error")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownMemoryPolicy {}
+
+/// What to do when an instruction writes below 0x200, where the interpreter
+/// and the font live. Writing there is legal but almost always a bug, and
+/// corrupts later FX29 digits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum WriteProtection {
+    /// Let the write through.
+    #[default]
+    Off,
+    /// Drop the write and log a warning.
+    Warn,
+    /// Stop with [`Chip8Error::ProtectedWrite`].
+    Strict,
+}
+
+/// Parses `off`, `warn` or `strict`.
+impl FromStr for WriteProtection {
+    type Err = UnknownWriteProtection;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(WriteProtection::Off),
+            "warn" => Ok(WriteProtection::Warn),
+            "strict" => Ok(WriteProtection::Strict),
+            _ => Err(UnknownWriteProtection),
+        }
+    }
+}
+
+impl fmt::Display for WriteProtection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            WriteProtection::Off => "off",
+            WriteProtection::Warn => "warn",
+            WriteProtection::Strict => "strict",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownWriteProtection;
+
+impl fmt::Display for UnknownWriteProtection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected one of off, warn, strict")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownWriteProtection {}
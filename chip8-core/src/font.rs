@@ -0,0 +1,135 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+
+/// The 4x5 hex digit sprites FX29 points I at. Interpreters drew their
+/// digits slightly differently, and a few games print them as part of the
+/// picture.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Font {
+    /// The digits most modern interpreters use, Octo and SUPER-CHIP among
+    /// them.
+    #[default]
+    Octo,
+    /// The COSMAC VIP's.
+    Vip,
+    /// The DREAM 6800's, three pixels wide.
+    Dream6800,
+    /// The ETI-660's, also three pixels wide.
+    Eti660,
+}
+
+const NAMES: [(&str, Font); 4] = [
+    ("octo", Font::Octo),
+    ("vip", Font::Vip),
+    ("dream6800", Font::Dream6800),
+    ("eti660", Font::Eti660),
+];
+
+#[rustfmt::skip]
+const OCTO: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, // 0 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0, 0x10, 0xF0, 0x10, 0xF0, // 2 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0, // 4 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40, // 6 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0, 0x10, 0xF0, // 8 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0, // A B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, // C D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80, // E F
+];
+
+#[rustfmt::skip]
+const VIP: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x60, 0x20, 0x20, 0x20, 0x70, // 0 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0, 0x10, 0xF0, 0x10, 0xF0, // 2 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, 0xF0, 0x80, 0xF0, 0x10, 0xF0, // 4 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x10, 0x10, 0x10, // 6 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0, 0x10, 0xF0, // 8 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, 0xF0, 0x50, 0x70, 0x50, 0xF0, // A B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, 0xF0, 0x50, 0x50, 0x50, 0xF0, // C D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80, // E F
+];
+
+#[rustfmt::skip]
+const DREAM_6800: [u8; 80] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, 0x40, 0x40, 0x40, 0x40, 0x40, // 0 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, 0xE0, 0x20, 0xE0, 0x20, 0xE0, // 2 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, 0xE0, 0x80, 0xE0, 0x20, 0xE0, // 4 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, 0xE0, 0x20, 0x20, 0x20, 0x20, // 6 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, 0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 8 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, 0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // A B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, 0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // C D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, 0xE0, 0x80, 0xC0, 0x80, 0x80, // E F
+];
+
+#[rustfmt::skip]
+const ETI_660: [u8; 80] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, 0x20, 0x20, 0x20, 0x20, 0x20, // 0 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, 0xE0, 0x20, 0xE0, 0x20, 0xE0, // 2 3
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, 0xE0, 0x80, 0xE0, 0x20, 0xE0, // 4 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, 0xE0, 0x20, 0x20, 0x20, 0x20, // 6 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, 0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 8 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, 0x80, 0x80, 0xE0, 0xA0, 0xE0, // A B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, 0x20, 0x20, 0xE0, 0xA0, 0xE0, // C D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, 0xE0, 0x80, 0xC0, 0x80, 0x80, // E F
+];
+
+impl Font {
+    /// The sprites for the digits 0 to F, 5 bytes each.
+    pub fn data(self) -> &'static [u8; 80] {
+        match self {
+            Font::Octo => &OCTO,
+            Font::Vip => &VIP,
+            Font::Dream6800 => &DREAM_6800,
+            Font::Eti660 => &ETI_660,
+        }
+    }
+
+    /// Where in memory the font is loaded. Octo's digits go at 0x50 like on
+    /// most modern interpreters. The VIP, DREAM 6800 and ETI-660 kept theirs
+    /// in the monitor ROM, outside the 4K a program can reach, so those are
+    /// put at the start of the interpreter area instead, which is what
+    /// emulators of those machines commonly do.
+    pub fn address(self) -> usize {
+        match self {
+            Font::Octo => 0x50,
+            Font::Vip | Font::Dream6800 | Font::Eti660 => 0x000,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownFont(pub String);
+
+impl fmt::Display for UnknownFont {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown font `{}`, expected one of ", self.0)?;
+        for (i, (name, _)) in NAMES.iter().enumerate() {
+            write!(f, "{}{}", if i > 0 { ", " } else { "" }, name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownFont {}
+
+/// Parses `octo`, `vip`, `dream6800` or `eti660`.
+impl FromStr for Font {
+    type Err = UnknownFont;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|&(_, font)| font)
+            .ok_or_else(|| UnknownFont(s.to_string()))
+    }
+}
+
+impl fmt::Display for Font {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = NAMES.iter().find(|(_, font)| font == self).unwrap().0;
+        write!(f, "{}", name)
+    }
+}
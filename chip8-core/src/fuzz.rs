@@ -0,0 +1,58 @@
+use crate::chip8::Chip8;
+use crate::error::{MemoryPolicy, WriteProtection};
+use crate::instruction::Opcode;
+use crate::quirks::Quirks;
+use alloc::vec::Vec;
+use arbitrary::Arbitrary;
+
+/// An arbitrary machine state for fuzzing. Nothing is validated, the index
+/// register, pc and call stack may all point outside of memory.
+#[derive(Debug, Arbitrary)]
+pub struct Chip8State {
+    pub memory: Vec<u8>,
+    pub registers: [u8; 16],
+    pub index: u16,
+    pub pc: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub call_stack: Vec<u16>,
+    pub keypad: [bool; 16],
+    pub quirks: Quirks,
+    pub memory_policy: MemoryPolicy,
+    pub write_protection: WriteProtection,
+}
+
+impl Chip8State {
+    /// Build a machine in this state. `memory` is copied over the start of
+    /// memory, fonts included, and truncated if it is too long.
+    pub fn build(&self) -> Chip8 {
+        let mut c8 = Chip8::builder()
+            .quirks(self.quirks)
+            .memory_policy(self.memory_policy)
+            .write_protection(self.write_protection)
+            .seed(0)
+            .build();
+        let len = self.memory.len().min(c8.memory.len());
+        c8.memory[..len].copy_from_slice(&self.memory[..len]);
+        c8.registers.copy_from_slice(&self.registers);
+        c8.index = self.index as usize;
+        c8.pc = self.pc as usize;
+        c8.delay_timer = self.delay_timer;
+        c8.sound_timer = self.sound_timer;
+        c8.call_stack = self.call_stack.iter().map(|&addr| addr as usize).collect();
+        c8.keypad.copy_from_slice(&self.keypad);
+        c8
+    }
+}
+
+/// Execute `opcode` against a machine in `state`, followed by one more
+/// instruction fetched from wherever that left the pc. Used by the fuzz
+/// targets in `chip8-core/fuzz`. Errors are fine, panics are bugs.
+pub fn fuzz_step(opcode: Opcode, state: &Chip8State) -> Chip8 {
+    let mut c8 = state.build();
+    if c8.exec_opcode(opcode).is_ok() {
+        let _ = c8.tick_cpu();
+    }
+    c8.tick_60hz();
+    c8
+}
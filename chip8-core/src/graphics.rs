@@ -0,0 +1,80 @@
+use crate::chip8::{Chip8, HEIGHT, WIDTH};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::pixelcolor::PixelColor;
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_graphics_core::Drawable;
+
+/// Draws the pixel buffer of a [`Chip8`] to an embedded-graphics
+/// `DrawTarget`, e.g. an SSD1306 or ST7789 display driver.
+///
+/// Lit pixels use the `on` colour and everything else `off`; each Chip-8
+/// pixel is drawn as a `scale` x `scale` square. A 128x64 monochrome OLED fits
+/// the whole screen at scale 2:
+///
+/// ```ignore
+/// Screen::new(&c8, BinaryColor::On, BinaryColor::Off)
+///     .scale(2)
+///     .draw(&mut display)?;
+/// display.flush()?;
+/// ```
+pub struct Screen<'a, C> {
+    chip8: &'a Chip8,
+    on: C,
+    off: C,
+    top_left: Point,
+    scale: u32,
+}
+
+impl<'a, C: PixelColor> Screen<'a, C> {
+    pub fn new(chip8: &'a Chip8, on: C, off: C) -> Self {
+        Screen {
+            chip8,
+            on,
+            off,
+            top_left: Point::zero(),
+            scale: 1,
+        }
+    }
+
+    pub fn scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn top_left(mut self, top_left: Point) -> Self {
+        self.top_left = top_left;
+        self
+    }
+
+    /// The area of the target covered when drawing.
+    pub fn area(&self) -> Rectangle {
+        Rectangle::new(
+            self.top_left,
+            Size::new(WIDTH as u32 * self.scale, HEIGHT as u32 * self.scale),
+        )
+    }
+}
+
+impl<C: PixelColor> Drawable for Screen<'_, C> {
+    type Color = C;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let scale = self.scale as usize;
+        let pixels = self.chip8.pixel_buffer();
+        let colors = (0..HEIGHT * scale).flat_map(move |y| {
+            (0..WIDTH * scale).map(move |x| {
+                if pixels[y / scale][x / scale] {
+                    self.on
+                } else {
+                    self.off
+                }
+            })
+        });
+        target.fill_contiguous(&self.area(), colors)
+    }
+}
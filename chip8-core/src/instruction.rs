@@ -0,0 +1,170 @@
+pub type Opcode = u16;
+
+/// The opcode pattern of every instruction the interpreter knows, as returned
+/// by [`Instruction::pattern`].
+pub const PATTERNS: [&str; 34] = [
+    "00E0", "00EE", "1NNN", "2NNN", "3XNN", "4XNN", "5XY0", "6XNN", "7XNN", "8XY0", "8XY1", "8XY2",
+    "8XY3", "8XY4", "8XY5", "8XY6", "8XY7", "8XYE", "9XY0", "ANNN", "BNNN", "CXNN", "DXYN", "EX9E",
+    "EXA1", "FX07", "FX0A", "FX15", "FX18", "FX1E", "FX29", "FX33", "FX55", "FX65",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    JumpTo(usize),
+    Subroutine(usize),
+
+    SkipIfRegisterEqualValue(usize, u8),
+    SkipIfRegisterNotEqualValue(usize, u8),
+
+    SkipIfRegisterEqualRegister(usize, usize),
+
+    SetRegisterToValue(usize, u8),
+    AddRegisterValue(usize, u8),
+
+    SetRegister(usize, usize),
+    SetRegisterOR(usize, usize),
+    SetRegisterAND(usize, usize),
+    SetRegisterXOR(usize, usize),
+    AddRegisterToRegister(usize, usize),
+    SubRegisterToRegister85(usize, usize),
+    SubRegisterToRegister87(usize, usize),
+    ShiftRight(usize, usize),
+
+    ShiftLeft(usize, usize),
+    SkipIfRegisterNotEqualRegister(usize, usize),
+
+    SetIndex(usize),
+
+    JumpRelV0(usize),
+    RandomAND(usize, u8),
+    Draw(usize, usize, u8),
+
+    SkipIfKey(usize),
+    SkipIfNotKey(usize),
+
+    SetToDelayTimer(usize),
+    GetKeyPress(usize),
+    SetDelayTimer(usize),
+    SetSoundTimer(usize),
+    AddToIndexRegister(usize),
+    SetIndexToSpriteAddr(usize),
+    Bcd(usize),
+    DumpRegistersTill(usize),
+    LoadRegistersTill(usize),
+
+    ClearScreen,
+    Return,
+    Noop,
+}
+
+impl Instruction {
+    /// The opcode pattern this instruction was decoded from, e.g. `8XY4`.
+    /// Anything the interpreter ignores is `????`.
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Instruction::ClearScreen => "00E0",
+            Instruction::Return => "00EE",
+            Instruction::JumpTo(_) => "1NNN",
+            Instruction::Subroutine(_) => "2NNN",
+            Instruction::SkipIfRegisterEqualValue(..) => "3XNN",
+            Instruction::SkipIfRegisterNotEqualValue(..) => "4XNN",
+            Instruction::SkipIfRegisterEqualRegister(..) => "5XY0",
+            Instruction::SetRegisterToValue(..) => "6XNN",
+            Instruction::AddRegisterValue(..) => "7XNN",
+            Instruction::SetRegister(..) => "8XY0",
+            Instruction::SetRegisterOR(..) => "8XY1",
+            Instruction::SetRegisterAND(..) => "8XY2",
+            Instruction::SetRegisterXOR(..) => "8XY3",
+            Instruction::AddRegisterToRegister(..) => "8XY4",
+            Instruction::SubRegisterToRegister85(..) => "8XY5",
+            Instruction::ShiftRight(..) => "8XY6",
+            Instruction::SubRegisterToRegister87(..) => "8XY7",
+            Instruction::ShiftLeft(..) => "8XYE",
+            Instruction::SkipIfRegisterNotEqualRegister(..) => "9XY0",
+            Instruction::SetIndex(_) => "ANNN",
+            Instruction::JumpRelV0(_) => "BNNN",
+            Instruction::RandomAND(..) => "CXNN",
+            Instruction::Draw(..) => "DXYN",
+            Instruction::SkipIfKey(_) => "EX9E",
+            Instruction::SkipIfNotKey(_) => "EXA1",
+            Instruction::SetToDelayTimer(_) => "FX07",
+            Instruction::GetKeyPress(_) => "FX0A",
+            Instruction::SetDelayTimer(_) => "FX15",
+            Instruction::SetSoundTimer(_) => "FX18",
+            Instruction::AddToIndexRegister(_) => "FX1E",
+            Instruction::SetIndexToSpriteAddr(_) => "FX29",
+            Instruction::Bcd(_) => "FX33",
+            Instruction::DumpRegistersTill(_) => "FX55",
+            Instruction::LoadRegistersTill(_) => "FX65",
+            Instruction::Noop => "????",
+        }
+    }
+}
+
+pub fn decode(oc: Opcode) -> Instruction {
+    let reg1: usize = ((oc & 0x0F00) >> 8) as usize;
+    let reg2: usize = ((oc & 0x00F0) >> 4) as usize;
+    let nnn: usize = (oc & 0x0FFF) as usize;
+    let nn: u8 = (oc & 0x00FF) as u8;
+    let n: u8 = (oc & 0x000F) as u8;
+
+    match oc & 0xF000 {
+        0x0000 => match oc & 0x00FF {
+            0x00E0 => Instruction::ClearScreen,
+            0x00EE => Instruction::Return,
+            _ => Instruction::Noop,
+        },
+        0x1000 => Instruction::JumpTo(nnn),
+        0x2000 => Instruction::Subroutine(nnn),
+
+        0x3000 => Instruction::SkipIfRegisterEqualValue(reg1, nn),
+        0x4000 => Instruction::SkipIfRegisterNotEqualValue(reg1, nn),
+
+        0x5000 => Instruction::SkipIfRegisterEqualRegister(reg1, reg2),
+
+        0x6000 => Instruction::SetRegisterToValue(reg1, nn),
+
+        0x7000 => Instruction::AddRegisterValue(reg1, nn),
+
+        0x8000 => match oc & 0x000F {
+            0x0000 => Instruction::SetRegister(reg1, reg2),
+            0x0001 => Instruction::SetRegisterOR(reg1, reg2),
+            0x0002 => Instruction::SetRegisterAND(reg1, reg2),
+            0x0003 => Instruction::SetRegisterXOR(reg1, reg2),
+            0x0004 => Instruction::AddRegisterToRegister(reg1, reg2),
+            0x0005 => Instruction::SubRegisterToRegister85(reg1, reg2),
+            0x0006 => Instruction::ShiftRight(reg1, reg2),
+            0x0007 => Instruction::SubRegisterToRegister87(reg1, reg2),
+            0x000E => Instruction::ShiftLeft(reg1, reg2),
+            _ => Instruction::Noop,
+        },
+
+        0x9000 => Instruction::SkipIfRegisterNotEqualRegister(reg1, reg2),
+        0xA000 => Instruction::SetIndex(nnn),
+        0xB000 => Instruction::JumpRelV0(nnn),
+        0xC000 => Instruction::RandomAND(reg1, nn),
+
+        0xD000 => Instruction::Draw(reg1, reg2, n),
+
+        0xE000 => match oc & 0x00FF {
+            0x009E => Instruction::SkipIfKey(reg1),
+            0x00A1 => Instruction::SkipIfNotKey(reg1),
+            _ => Instruction::Noop,
+        },
+
+        0xF000 => match oc & 0x00FF {
+            0x0007 => Instruction::SetToDelayTimer(reg1),
+            0x000A => Instruction::GetKeyPress(reg1),
+            0x0015 => Instruction::SetDelayTimer(reg1),
+            0x0018 => Instruction::SetSoundTimer(reg1),
+            0x001E => Instruction::AddToIndexRegister(reg1),
+            0x0029 => Instruction::SetIndexToSpriteAddr(reg1),
+            0x0033 => Instruction::Bcd(reg1),
+            0x0055 => Instruction::DumpRegistersTill(reg1),
+            0x0065 => Instruction::LoadRegistersTill(reg1),
+            _ => Instruction::Noop,
+        },
+
+        _ => Instruction::Noop,
+    }
+}
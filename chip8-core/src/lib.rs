@@ -0,0 +1,66 @@
+//! Platform independent Chip-8 interpreter.
+//!
+//! The core owns memory, registers, timers, the keypad and the pixel buffer,
+//! and knows nothing about windows, audio devices or input events. Nor about
+//! time: frontends clock it by calling [`Chip8::tick_cpu`] for every
+//! instruction and [`Chip8::tick_60hz`] for every frame, and read the pixel
+//! buffer back after a draw. Bots can use [`Agent`] instead, which runs
+//! whole frames at a time. Experimental peripherals can be added with a
+//! [`Plugin`].
+//!
+//! The crate is `no_std` (it still needs an allocator) when the default `std`
+//! feature is disabled, so it can be used on microcontrollers. Enable the
+//! `embedded-graphics` feature to draw the screen to any embedded-graphics
+//! `DrawTarget`, see [`Screen`]. The `fuzzing` feature exposes [`fuzz_step`]
+//! for the cargo-fuzz targets.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod access;
+mod agent;
+mod builder;
+mod chip8;
+mod error;
+mod font;
+#[cfg(feature = "fuzzing")]
+mod fuzz;
+#[cfg(feature = "embedded-graphics")]
+mod graphics;
+mod instruction;
+mod platform;
+mod plugin;
+#[cfg(test)]
+mod proptests;
+mod quirks;
+mod rng;
+mod rom_id;
+mod state;
+#[cfg(test)]
+mod tests;
+
+pub use crate::access::{AccessCounts, CodeWrite};
+pub use crate::agent::{Agent, PackedScreen};
+pub use crate::builder::Chip8Builder;
+#[cfg(feature = "std")]
+pub use crate::chip8::random_seed;
+pub use crate::chip8::{
+    Chip8, DEFAULT_MEMORY_SIZE, DEFAULT_SPEED, DEFAULT_STACK_DEPTH, HEIGHT, MAX_MEMORY_SIZE,
+    MAX_STACK_DEPTH, MIN_MEMORY_SIZE, WIDTH,
+};
+pub use crate::error::{
+    Chip8Error, LoadError, MemoryPolicy, UnknownMemoryPolicy, UnknownWriteProtection,
+    WriteProtection,
+};
+pub use crate::font::{Font, UnknownFont};
+#[cfg(feature = "fuzzing")]
+pub use crate::fuzz::{fuzz_step, Chip8State};
+#[cfg(feature = "embedded-graphics")]
+pub use crate::graphics::Screen;
+pub use crate::instruction::{decode, Instruction, Opcode, PATTERNS};
+pub use crate::platform::{Platform, UnknownPlatform};
+pub use crate::plugin::Plugin;
+pub use crate::quirks::{Quirks, UnknownQuirk};
+pub use crate::rom_id::RomId;
+pub use crate::state::StateError;
@@ -0,0 +1,112 @@
+use crate::font::Font;
+use crate::quirks::Quirks;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+
+/// Interpreters whose whole set of quirks can be picked at once, for games
+/// written against one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// The original COSMAC VIP interpreter.
+    Vip,
+    /// SUPER-CHIP 1.1 on the HP48.
+    Schip,
+    /// XO-CHIP, as implemented by Octo.
+    XoChip,
+}
+
+const NAMES: [(&str, Platform); 3] = [
+    ("vip", Platform::Vip),
+    ("schip", Platform::Schip),
+    ("xo-chip", Platform::XoChip),
+];
+
+impl Platform {
+    /// The digits the platform's interpreter drew with FX29.
+    pub fn font(self) -> Font {
+        match self {
+            Platform::Vip => Font::Vip,
+            Platform::Schip | Platform::XoChip => Font::Octo,
+        }
+    }
+
+    /// Bytes of memory programs could use: 4K, or the 64K XO-CHIP addresses.
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::Vip | Platform::Schip => 0x1000,
+            Platform::XoChip => 0x10000,
+        }
+    }
+
+    /// Nested subroutine calls the interpreter had room for. The VIP kept 12
+    /// return addresses at the top of its RAM.
+    pub fn stack_depth(self) -> usize {
+        match self {
+            Platform::Vip => 12,
+            Platform::Schip | Platform::XoChip => 16,
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Vip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: true,
+                clip_sprites: true,
+            },
+            Platform::Schip => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: true,
+                logic_resets_vf: false,
+                clip_sprites: true,
+            },
+            Platform::XoChip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: false,
+                clip_sprites: false,
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownPlatform(pub String);
+
+impl fmt::Display for UnknownPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown platform `{}`, expected one of ", self.0)?;
+        for (i, (name, _)) in NAMES.iter().enumerate() {
+            write!(f, "{}{}", if i > 0 { ", " } else { "" }, name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownPlatform {}
+
+/// Parses `vip`, `schip` or `xo-chip`.
+impl FromStr for Platform {
+    type Err = UnknownPlatform;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|&(_, platform)| platform)
+            .ok_or_else(|| UnknownPlatform(s.to_string()))
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = NAMES.iter().find(|(_, p)| p == self).unwrap().0;
+        write!(f, "{}", name)
+    }
+}
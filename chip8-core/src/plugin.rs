@@ -0,0 +1,64 @@
+use crate::chip8::Chip8;
+use crate::error::Chip8Error;
+use crate::instruction::Opcode;
+use core::ops::Range;
+
+/// An extension to the interpreter for experimental peripherals, added with
+/// [`Chip8::add_plugin`]. A plugin can take over opcodes that mean nothing to
+/// the interpreter, and memory that reads and writes through I go to instead
+/// of RAM, e.g. a serial port or extra storage.
+///
+/// Every method has a default that claims nothing, so a plugin only
+/// implements the half it needs. Plugins are `Send` so a machine can still
+/// move between threads.
+///
+/// ```
+/// use chip8_core::{Chip8, Chip8Error, Opcode, Plugin};
+///
+/// // 0x0NNN sets V0 to NN, a stand-in for reading some device
+/// struct Device;
+///
+/// impl Plugin for Device {
+///     fn claims_opcode(&self, oc: Opcode) -> bool {
+///         oc & 0xF000 == 0
+///     }
+///
+///     fn execute(&mut self, oc: Opcode, c8: &mut Chip8) -> Result<(), Chip8Error> {
+///         c8.registers_mut()[0] = oc as u8;
+///         Ok(())
+///     }
+/// }
+///
+/// let mut c8 = Chip8::new();
+/// c8.add_plugin(Box::new(Device));
+/// c8.load_rom(&[0x00, 0x42]).unwrap();
+/// c8.tick_cpu().unwrap();
+/// assert_eq!(c8.registers()[0], 0x42);
+/// assert_eq!(c8.pc(), 0x202);
+/// ```
+pub trait Plugin: Send {
+    /// Whether the plugin handles `oc`. Only asked about opcodes the
+    /// interpreter has no instruction for, so built in ones can't be replaced.
+    fn claims_opcode(&self, _oc: Opcode) -> bool {
+        false
+    }
+
+    /// Carry out a claimed opcode. The pc has already moved past it.
+    fn execute(&mut self, _oc: Opcode, _c8: &mut Chip8) -> Result<(), Chip8Error> {
+        Ok(())
+    }
+
+    /// The addresses the plugin maps. DXYN, FX33, FX55 and FX65 read and
+    /// write these through [`read`](Plugin::read) and
+    /// [`write`](Plugin::write) instead of memory; the program itself still
+    /// runs from memory.
+    fn region(&self) -> Option<Range<usize>> {
+        None
+    }
+
+    fn read(&mut self, _addr: usize) -> u8 {
+        0
+    }
+
+    fn write(&mut self, _addr: usize, _value: u8) {}
+}
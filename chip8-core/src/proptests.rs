@@ -0,0 +1,85 @@
+// Flag behaviour of the ALU instructions over every operand value, checked
+// against a straightforward model. VF may be either operand or the
+// destination, in which case the flag is written after the result.
+use crate::chip8::Chip8;
+use crate::quirks::Quirks;
+use proptest::prelude::*;
+
+// what VX and VF should hold after 8XYn, given the original VX and VY
+fn model(op: u16, vx: u8, vy: u8) -> (u8, u8) {
+    match op {
+        0x4 => {
+            let sum = vx as u16 + vy as u16;
+            (sum as u8, (sum > 0xFF) as u8)
+        }
+        0x5 => (vx.wrapping_sub(vy), (vx >= vy) as u8),
+        0x7 => (vy.wrapping_sub(vx), (vy >= vx) as u8),
+        0x6 => (vx >> 1, vx & 1),
+        0xE => (vx << 1, vx >> 7),
+        _ => unreachable!(),
+    }
+}
+
+fn check(op: u16, x: usize, y: usize, vx: u8, vy: u8, vf: u8, shift_uses_vy: bool) {
+    let mut c8 = Chip8::builder()
+        .quirks(Quirks {
+            shift_uses_vy,
+            ..Quirks::default()
+        })
+        .build();
+    c8.registers[0xF] = vf;
+    c8.registers[y] = vy;
+    c8.registers[x] = vx;
+    // the operands as the instruction sees them, after any aliasing
+    let (vx, vy) = (c8.registers[x], c8.registers[y]);
+    let before = c8.registers.clone();
+
+    c8.exec_opcode(0x8000 | (x as u16) << 8 | (y as u16) << 4 | op)
+        .unwrap();
+
+    let src = if shift_uses_vy && (op == 0x6 || op == 0xE) {
+        vy
+    } else {
+        vx
+    };
+    let (result, flag) = model(op, src, vy);
+    let mut expected = before;
+    expected[x] = result;
+    expected[0xF] = flag;
+    assert_eq!(c8.registers, expected, "8{:X}{:X}{:X}", x, y, op);
+}
+
+fn alu_op() -> impl Strategy<Value = u16> {
+    prop::sample::select(vec![0x4, 0x5, 0x7, 0x6, 0xE])
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    fn alu_flags(
+        op in alu_op(),
+        x in 0usize..16,
+        y in 0usize..16,
+        vx: u8,
+        vy: u8,
+        vf: u8,
+        shift_uses_vy: bool,
+    ) {
+        check(op, x, y, vx, vy, vf, shift_uses_vy);
+    }
+
+    #[test]
+    fn alu_flags_with_vf_operand(
+        op in alu_op(),
+        other in 0usize..16,
+        vf_is_x: bool,
+        vx: u8,
+        vy: u8,
+        vf: u8,
+        shift_uses_vy: bool,
+    ) {
+        let (x, y) = if vf_is_x { (0xF, other) } else { (other, 0xF) };
+        check(op, x, y, vx, vy, vf, shift_uses_vy);
+    }
+}
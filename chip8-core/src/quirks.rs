@@ -0,0 +1,135 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+/// Behaviours that differ between Chip-8 interpreters. Games are written
+/// against one interpreter or another, so some only run correctly with
+/// particular quirks enabled. The default matches this emulator's original
+/// behaviour.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct Quirks {
+    /// 8XY6/8XYE shift VY and store the result in VX, instead of shifting VX
+    /// in place.
+    pub shift_uses_vy: bool,
+    /// FX55/FX65 leave I pointing just past the last register accessed.
+    pub load_store_increments_i: bool,
+    /// BNNN jumps to NNN + VX (X being the high nibble of NNN) instead of
+    /// NNN + V0.
+    pub jump_uses_vx: bool,
+    /// 8XY1/8XY2/8XY3 reset VF to zero.
+    pub logic_resets_vf: bool,
+    /// Sprites are clipped at the screen edges instead of wrapping around.
+    pub clip_sprites: bool,
+}
+
+const NAMES: [&str; 5] = ["shift-vy", "load-store-i", "jump-vx", "vf-reset", "clip"];
+
+impl Quirks {
+    /// The name of every quirk, as accepted by `set` and `from_str`.
+    pub fn names() -> &'static [&'static str] {
+        &NAMES
+    }
+
+    /// Every possible set of quirks, starting with none.
+    pub fn combinations() -> impl Iterator<Item = Quirks> {
+        (0..1u32 << NAMES.len()).map(|bits| {
+            let mut quirks = Quirks::default();
+            for (i, name) in NAMES.iter().enumerate() {
+                // the names are all known
+                quirks.set(name, bits & (1 << i) != 0).unwrap();
+            }
+            quirks
+        })
+    }
+
+    /// Turn a single quirk, named as in `from_str`, on or off.
+    pub fn set(&mut self, name: &str, on: bool) -> Result<(), UnknownQuirk> {
+        *self
+            .flag_mut(name)
+            .ok_or_else(|| UnknownQuirk(name.to_string()))? = on;
+        Ok(())
+    }
+
+    /// Whether a single quirk, named as in `from_str`, is on.
+    pub fn get(&self, name: &str) -> Result<bool, UnknownQuirk> {
+        NAMES
+            .iter()
+            .position(|n| *n == name)
+            .map(|i| self.flags()[i])
+            .ok_or_else(|| UnknownQuirk(name.to_string()))
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "shift-vy" => Some(&mut self.shift_uses_vy),
+            "load-store-i" => Some(&mut self.load_store_increments_i),
+            "jump-vx" => Some(&mut self.jump_uses_vx),
+            "vf-reset" => Some(&mut self.logic_resets_vf),
+            "clip" => Some(&mut self.clip_sprites),
+            _ => None,
+        }
+    }
+
+    fn flags(&self) -> [bool; 5] {
+        [
+            self.shift_uses_vy,
+            self.load_store_increments_i,
+            self.jump_uses_vx,
+            self.logic_resets_vf,
+            self.clip_sprites,
+        ]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownQuirk(pub String);
+
+impl fmt::Display for UnknownQuirk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown quirk `{}`, expected one of {}",
+            self.0,
+            NAMES.join(", ")
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownQuirk {}
+
+/// Parses a comma separated list of quirk names to enable, e.g.
+/// `shift-vy,clip`. `none` (or an empty string) is the default set.
+impl FromStr for Quirks {
+    type Err = UnknownQuirk;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut quirks = Quirks::default();
+        for name in s.split(',').map(str::trim) {
+            if name.is_empty() || name == "none" {
+                continue;
+            }
+            quirks.set(name, true)?;
+        }
+        Ok(quirks)
+    }
+}
+
+/// Formats as the comma separated list accepted by `from_str`.
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let enabled: Vec<&str> = NAMES
+            .iter()
+            .zip(self.flags().iter())
+            .filter(|(_, &on)| on)
+            .map(|(name, _)| *name)
+            .collect();
+        if enabled.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", enabled.join(","))
+        }
+    }
+}
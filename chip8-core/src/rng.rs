@@ -0,0 +1,39 @@
+// The CXNN random number generator: the 128-bit PCG multiplier generator
+// (Mcg128Xsl64) that rand's SmallRng is on 64-bit targets, written out here so
+// its state can go into save states, and so 32-bit targets draw the same
+// numbers from a seed too.
+use rand::SeedableRng;
+
+const MULTIPLIER: u128 = 0x2360_ED05_1FC6_5DA4_4385_DF64_9FCC_F645;
+
+#[derive(Clone)]
+pub(crate) struct Rng {
+    state: u128,
+}
+
+impl Rng {
+    pub(crate) fn from_state(state: u128) -> Rng {
+        // the state has to stay odd
+        Rng { state: state | 1 }
+    }
+
+    pub(crate) fn state(&self) -> u128 {
+        self.state
+    }
+
+    pub(crate) fn next_byte(&mut self) -> u8 {
+        self.state = self.state.wrapping_mul(MULTIPLIER);
+        let rot = (self.state >> 122) as u32;
+        let xsl = (self.state >> 64) as u64 ^ self.state as u64;
+        xsl.rotate_right(rot) as u8
+    }
+}
+
+// for `seed_from_u64`, which spreads a seed over the state the way SmallRng's does
+impl SeedableRng for Rng {
+    type Seed = [u8; 16];
+
+    fn from_seed(seed: [u8; 16]) -> Rng {
+        Rng::from_state(u128::from_le_bytes(seed))
+    }
+}
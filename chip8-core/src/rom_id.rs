@@ -0,0 +1,128 @@
+use alloc::string::String;
+use core::fmt;
+use core::fmt::Write;
+
+/// Size, CRC32 and SHA-1 of a ROM, so two people can check they are running
+/// the same binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomId {
+    pub size: usize,
+    pub crc32: u32,
+    pub sha1: [u8; 20],
+}
+
+impl RomId {
+    pub fn of(data: &[u8]) -> RomId {
+        RomId {
+            size: data.len(),
+            crc32: crc32(data),
+            sha1: sha1(data),
+        }
+    }
+
+    /// The SHA-1 as 40 hex digits.
+    pub fn sha1_hex(&self) -> String {
+        let mut hex = String::new();
+        for byte in &self.sha1 {
+            // writing to a String can't fail
+            write!(hex, "{:02x}", byte).unwrap();
+        }
+        hex
+    }
+}
+
+/// Formats as `sha1 <hex> crc32 <hex> (<size> bytes)`.
+impl fmt::Display for RomId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sha1 {} crc32 {:08x} ({} bytes)",
+            self.sha1_hex(),
+            self.crc32,
+            self.size
+        )
+    }
+}
+
+// the zlib / PNG CRC, bit by bit since ROMs are at most a few kilobytes
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// FIPS 180-4, section 6.1
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    // the message, a 1 bit, zeros, then the length in bits, in 64 byte blocks
+    let bits = (data.len() as u64).wrapping_mul(8);
+    let padded_len = (data.len() + 9).div_ceil(64) * 64;
+    let byte = |i: usize| -> u8 {
+        if i < data.len() {
+            data[i]
+        } else if i == data.len() {
+            0x80
+        } else if i >= padded_len - 8 {
+            (bits >> (8 * (padded_len - 1 - i))) as u8
+        } else {
+            0
+        }
+    };
+
+    for block in (0..padded_len).step_by(64) {
+        let mut w = [0u32; 80];
+        for (t, word) in w.iter_mut().take(16).enumerate() {
+            let at = block + 4 * t;
+            *word = u32::from_be_bytes([byte(at), byte(at + 1), byte(at + 2), byte(at + 3)]);
+        }
+        for t in 16..80 {
+            w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (t, &word) in w.iter().enumerate() {
+            let (f, k) = match t {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0; 20];
+    for (chunk, word) in out.chunks_mut(4).zip(h.iter()) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
@@ -0,0 +1,193 @@
+use crate::chip8::{Chip8, HEIGHT, WIDTH};
+use crate::rng::Rng;
+use crate::rom_id::RomId;
+use alloc::vec::Vec;
+
+// Save state layout, all multi-byte values little endian:
+//
+//   magic "C8ST", version u8
+//   ROM loaded u8, then if it is 1: ROM size u32, CRC32 u32, SHA-1 20 bytes
+//   memory length u32, memory bytes
+//   16 registers, index u32 (u16 before version 4), pc u16, delay timer u8,
+//   sound timer u8
+//   call stack depth u16, followed by that many u16 return addresses
+//   pixel buffer, one byte per pixel, row major
+//   CXNN seed u64, random number generator state u128
+//
+// The keypad is not saved, it is owned by whoever is feeding input. Version 1
+// states, from before the ROM was recorded, and version 2 states, from before
+// the random number generator was, are still accepted and keep the machine's
+// own generator. I can run past 0xFFFF with FX1E, so it has 32 bits since
+// version 4.
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    MemorySizeMismatch,
+    StackTooDeep,
+    /// The state was saved while running a different ROM
+    WrongRom,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < n {
+            return Err(StateError::Truncated);
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, StateError> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    fn u128(&mut self) -> Result<u128, StateError> {
+        let mut b = [0; 16];
+        b.copy_from_slice(self.take(16)?);
+        Ok(u128::from_le_bytes(b))
+    }
+}
+
+impl Chip8 {
+    /// Serialize the machine state so it can be restored with `load_state`.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+
+        match &self.rom {
+            Some(rom) => {
+                out.push(1);
+                out.extend_from_slice(&(rom.size as u32).to_le_bytes());
+                out.extend_from_slice(&rom.crc32.to_le_bytes());
+                out.extend_from_slice(&rom.sha1);
+            }
+            None => out.push(0),
+        }
+
+        out.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.memory);
+
+        out.extend_from_slice(&self.registers);
+        out.extend_from_slice(&(self.index as u32).to_le_bytes());
+        out.extend_from_slice(&(self.pc as u16).to_le_bytes());
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+
+        out.extend_from_slice(&(self.call_stack.len() as u16).to_le_bytes());
+        for addr in &self.call_stack {
+            out.extend_from_slice(&(*addr as u16).to_le_bytes());
+        }
+
+        for row in &self.pixel_buffer {
+            out.extend(row.iter().map(|&p| p as u8));
+        }
+
+        out.extend_from_slice(&self.seed().to_le_bytes());
+        out.extend_from_slice(&self.rng.state().to_le_bytes());
+        out
+    }
+
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut r = Reader { data };
+        if r.take(4)? != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = r.u8()?;
+        if version == 0 || version > VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let rom = if version >= 2 && r.u8()? == 1 {
+            let size = r.u32()? as usize;
+            let crc32 = r.u32()?;
+            let mut sha1 = [0; 20];
+            sha1.copy_from_slice(r.take(20)?);
+            Some(RomId { size, crc32, sha1 })
+        } else {
+            None
+        };
+        if let (Some(saved), Some(current)) = (&rom, &self.rom) {
+            if saved != current {
+                return Err(StateError::WrongRom);
+            }
+        }
+
+        let memory_len = r.u32()? as usize;
+        if memory_len != self.memory.len() {
+            return Err(StateError::MemorySizeMismatch);
+        }
+        let memory = r.take(memory_len)?;
+        let registers = r.take(16)?;
+        let index = if version >= 4 {
+            r.u32()? as usize
+        } else {
+            r.u16()? as usize
+        };
+        let pc = r.u16()? as usize;
+        let delay_timer = r.u8()?;
+        let sound_timer = r.u8()?;
+
+        let depth = r.u16()? as usize;
+        if depth > self.stack_depth {
+            return Err(StateError::StackTooDeep);
+        }
+        let mut call_stack = Vec::with_capacity(depth);
+        for _ in 0..depth {
+            call_stack.push(r.u16()? as usize);
+        }
+
+        let pixels = r.take(WIDTH * HEIGHT)?;
+        let rng = if version >= 3 {
+            Some((r.u64()?, r.u128()?))
+        } else {
+            None
+        };
+
+        // only touch the machine once the whole state has been validated
+        self.memory.copy_from_slice(memory);
+        self.registers.copy_from_slice(registers);
+        self.index = index;
+        self.pc = pc;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.call_stack = call_stack;
+        self.rom = rom.or(self.rom);
+        for (row, src) in self.pixel_buffer.iter_mut().zip(pixels.chunks(WIDTH)) {
+            for (p, &b) in row.iter_mut().zip(src) {
+                *p = b != 0;
+            }
+        }
+        if let Some((seed, state)) = rng {
+            self.seed = seed;
+            self.rng = Rng::from_state(state);
+        }
+        self.draw_flag = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,799 @@
+use crate::access::CodeWrite;
+use crate::agent::Agent;
+use crate::chip8::{Chip8, MAX_MEMORY_SIZE};
+use crate::error::{Chip8Error, LoadError, MemoryPolicy, WriteProtection};
+use crate::font::Font;
+use crate::instruction::{decode, Opcode, PATTERNS};
+use crate::platform::Platform;
+use crate::plugin::Plugin;
+use crate::quirks::Quirks;
+use crate::rom_id::RomId;
+use crate::state::StateError;
+use core::ops::Range;
+
+const START: usize = 0x200;
+
+fn machine(registers: &[(usize, u8)]) -> Chip8 {
+    let mut c8 = Chip8::new();
+    for &(reg, val) in registers {
+        c8.registers[reg] = val;
+    }
+    c8
+}
+
+fn run(registers: &[(usize, u8)], oc: u16) -> Chip8 {
+    let mut c8 = machine(registers);
+    c8.exec_opcode(oc).unwrap();
+    c8
+}
+
+fn with_quirks(quirks: &str) -> Chip8 {
+    Chip8::builder()
+        .quirks(quirks.parse::<Quirks>().unwrap())
+        .build()
+}
+
+#[test]
+fn every_pattern_decodes_to_itself() {
+    for pattern in PATTERNS.iter() {
+        let oc = u16::from_str_radix(&pattern.replace(|c| "XYN".contains(c), "0"), 16).unwrap();
+        assert_eq!(decode(oc).pattern(), *pattern);
+    }
+}
+
+#[test]
+fn skip_if_register_equal_value() {
+    assert_eq!(run(&[(1, 0x42)], 0x3142).pc, START + 4);
+    assert_eq!(run(&[(1, 0x41)], 0x3142).pc, START + 2);
+}
+
+#[test]
+fn skip_if_register_not_equal_value() {
+    assert_eq!(run(&[(1, 0x41)], 0x4142).pc, START + 4);
+    assert_eq!(run(&[(1, 0x42)], 0x4142).pc, START + 2);
+}
+
+#[test]
+fn skip_if_registers_equal() {
+    assert_eq!(run(&[(1, 7), (2, 7)], 0x5120).pc, START + 4);
+    assert_eq!(run(&[(1, 7), (2, 8)], 0x5120).pc, START + 2);
+}
+
+#[test]
+fn skip_if_registers_not_equal() {
+    assert_eq!(run(&[(1, 7), (2, 8)], 0x9120).pc, START + 4);
+    assert_eq!(run(&[(1, 7), (2, 7)], 0x9120).pc, START + 2);
+}
+
+#[test]
+fn set_and_add_value() {
+    let c8 = run(&[], 0x6A12);
+    assert_eq!(c8.registers[0xA], 0x12);
+    assert_eq!(c8.pc, START + 2);
+
+    // 7XNN wraps and never touches VF
+    let c8 = run(&[(3, 0xFF), (0xF, 5)], 0x7302);
+    assert_eq!(c8.registers[3], 0x01);
+    assert_eq!(c8.registers[0xF], 5);
+}
+
+#[test]
+fn register_to_register_logic() {
+    assert_eq!(run(&[(1, 0), (2, 0x5A)], 0x8120).registers[1], 0x5A);
+    assert_eq!(run(&[(1, 0xF0), (2, 0x0F)], 0x8121).registers[1], 0xFF);
+    assert_eq!(run(&[(1, 0xF0), (2, 0x3C)], 0x8122).registers[1], 0x30);
+    assert_eq!(run(&[(1, 0xF0), (2, 0x3C)], 0x8123).registers[1], 0xCC);
+}
+
+#[test]
+fn add_registers_sets_carry() {
+    let c8 = run(&[(1, 0xF0), (2, 0x20)], 0x8124);
+    assert_eq!(c8.registers[1], 0x10);
+    assert_eq!(c8.registers[0xF], 1);
+
+    let c8 = run(&[(1, 0x10), (2, 0x20)], 0x8124);
+    assert_eq!(c8.registers[1], 0x30);
+    assert_eq!(c8.registers[0xF], 0);
+}
+
+#[test]
+fn add_registers_carry_uses_original_operands() {
+    // 0x80 + 0x40 does not overflow, but re-adding VY to the updated VX would
+    let c8 = run(&[(1, 0x80), (2, 0x40)], 0x8124);
+    assert_eq!(c8.registers[1], 0xC0);
+    assert_eq!(c8.registers[0xF], 0);
+}
+
+#[test]
+fn sub_registers_sets_not_borrow() {
+    let c8 = run(&[(1, 0x30), (2, 0x10)], 0x8125);
+    assert_eq!(c8.registers[1], 0x20);
+    assert_eq!(c8.registers[0xF], 1);
+
+    let c8 = run(&[(1, 0x10), (2, 0x30)], 0x8125);
+    assert_eq!(c8.registers[1], 0xE0);
+    assert_eq!(c8.registers[0xF], 0);
+}
+
+#[test]
+fn reverse_sub_registers_sets_not_borrow() {
+    let c8 = run(&[(1, 0x10), (2, 0x30)], 0x8127);
+    assert_eq!(c8.registers[1], 0x20);
+    assert_eq!(c8.registers[0xF], 1);
+
+    let c8 = run(&[(1, 0x30), (2, 0x10)], 0x8127);
+    assert_eq!(c8.registers[1], 0xE0);
+    assert_eq!(c8.registers[0xF], 0);
+}
+
+#[test]
+fn shifts_put_shifted_out_bit_in_vf() {
+    let c8 = run(&[(1, 0b0000_0101)], 0x8126);
+    assert_eq!(c8.registers[1], 0b0000_0010);
+    assert_eq!(c8.registers[0xF], 1);
+
+    let c8 = run(&[(1, 0b1000_0010)], 0x812E);
+    assert_eq!(c8.registers[1], 0b0000_0100);
+    assert_eq!(c8.registers[0xF], 1);
+
+    let c8 = run(&[(1, 0b0100_0000)], 0x812E);
+    assert_eq!(c8.registers[1], 0b1000_0000);
+    assert_eq!(c8.registers[0xF], 0);
+}
+
+#[test]
+fn shift_quirk_reads_vy() {
+    let mut c8 = with_quirks("shift-vy");
+    c8.registers[1] = 0xFF;
+    c8.registers[2] = 0b0000_0100;
+    c8.exec_opcode(0x8126).unwrap();
+    assert_eq!(c8.registers[1], 0b0000_0010);
+    assert_eq!(c8.registers[0xF], 0);
+}
+
+#[test]
+fn logic_quirk_resets_vf() {
+    let mut c8 = with_quirks("vf-reset");
+    c8.registers[0xF] = 1;
+    c8.exec_opcode(0x8121).unwrap();
+    assert_eq!(c8.registers[0xF], 0);
+}
+
+#[test]
+fn jumps_and_subroutines() {
+    assert_eq!(run(&[], 0x1ABC).pc, 0xABC);
+    assert_eq!(run(&[(0, 0x10)], 0xB300).pc, 0x310);
+
+    let mut c8 = machine(&[]);
+    c8.exec_opcode(0x2400).unwrap();
+    assert_eq!(c8.pc, 0x400);
+    c8.exec_opcode(0x00EE).unwrap();
+    assert_eq!(c8.pc, START + 2);
+}
+
+#[test]
+fn call_stack_overflow_and_underflow() {
+    let mut c8 = Chip8::builder().stack_depth(2).build();
+    c8.exec_opcode(0x2300).unwrap();
+    c8.exec_opcode(0x2400).unwrap();
+    assert_eq!(
+        c8.exec_opcode(0x2500),
+        Err(Chip8Error::StackOverflow { pc: 0x400 })
+    );
+
+    c8.exec_opcode(0x00EE).unwrap();
+    c8.exec_opcode(0x00EE).unwrap();
+    assert_eq!(c8.pc, START + 2);
+    assert_eq!(
+        c8.exec_opcode(0x00EE),
+        Err(Chip8Error::StackUnderflow { pc: START + 2 })
+    );
+}
+
+#[test]
+fn pc_leaving_program_memory() {
+    let mut c8 = machine(&[]);
+    c8.load_rom(&[0x1F, 0xFF]).unwrap();
+    c8.tick_cpu().unwrap();
+    assert_eq!(c8.tick_cpu(), Err(Chip8Error::PcOutOfRange { pc: 0xFFF }));
+
+    let mut c8 = machine(&[]);
+    c8.load_rom(&[0x60, 0x01, 0x10, 0x50]).unwrap();
+    c8.tick_cpu().unwrap();
+    c8.tick_cpu().unwrap();
+    assert_eq!(
+        c8.tick_cpu(),
+        Err(Chip8Error::PcInInterpreterArea { pc: 0x50 })
+    );
+    assert_eq!(
+        c8.recent_instructions().collect::<Vec<_>>(),
+        vec![(START, 0x6001), (START + 2, 0x1050)]
+    );
+}
+
+#[test]
+fn odd_pc_is_an_error_only_when_alignment_is_required() {
+    let mut c8 = machine(&[]);
+    c8.load_rom(&[0x12, 0x03]).unwrap();
+    c8.tick_cpu().unwrap();
+    assert_eq!(c8.pc, 0x203);
+    c8.tick_cpu().unwrap();
+
+    let mut c8 = Chip8::builder().aligned_pc(true).build();
+    c8.load_rom(&[0x12, 0x03]).unwrap();
+    c8.tick_cpu().unwrap();
+    assert_eq!(c8.tick_cpu(), Err(Chip8Error::PcMisaligned { pc: 0x203 }));
+}
+
+#[test]
+fn index_past_memory_is_only_checked_when_used() {
+    // A FFF; 60FF; F01E leaves I past the end, the draw wraps it back
+    let mut c8 = machine(&[]);
+    c8.load_rom(&[0xAF, 0xFF, 0x60, 0xFF, 0xF0, 0x1E, 0xD0, 0x01])
+        .unwrap();
+    for _ in 0..4 {
+        c8.tick_cpu().unwrap();
+    }
+    assert_eq!(c8.index, 0x10FE);
+}
+
+#[test]
+fn jump_quirk_uses_vx() {
+    let mut c8 = with_quirks("jump-vx");
+    c8.registers[0] = 0x10;
+    c8.registers[3] = 0x20;
+    c8.exec_opcode(0xB300).unwrap();
+    assert_eq!(c8.pc, 0x320);
+}
+
+#[test]
+fn index_instructions() {
+    assert_eq!(run(&[], 0xA123).index, 0x123);
+    assert_eq!(run(&[(4, 0xA)], 0xF429).index, 0x50 + 5 * 0xA);
+
+    let mut c8 = run(&[(2, 0x10)], 0xA100);
+    c8.exec_opcode(0xF21E).unwrap();
+    assert_eq!(c8.index, 0x110);
+    assert_eq!(c8.registers[0xF], 0);
+}
+
+#[test]
+fn font_sets_are_loaded_where_fx29_points() {
+    assert_eq!("eti660".parse(), Ok(Font::Eti660));
+    assert!("chip48".parse::<Font>().is_err());
+    assert_eq!(Platform::Vip.font(), Font::Vip);
+
+    for font in [Font::Octo, Font::Vip, Font::Dream6800, Font::Eti660] {
+        let mut c8 = Chip8::builder().font(font).build();
+        c8.registers[3] = 0xB;
+        c8.exec_opcode(0xF329).unwrap();
+        assert_eq!(c8.index, font.address() + 5 * 0xB);
+        assert_eq!(&c8.memory[c8.index..c8.index + 5], &font.data()[55..60]);
+    }
+}
+
+#[test]
+fn debug_port_collects_writes() {
+    let mut c8 = Chip8::builder().debug_port(Some(0xF00)).build();
+    c8.load_rom(&[0x60, b'h', 0x61, b'i', 0xAF, 0x00, 0xF0, 0x55, 0xF1, 0x55])
+        .unwrap();
+    for _ in 0..5 {
+        c8.tick_cpu().unwrap();
+    }
+    assert_eq!(c8.take_debug_output(), b"hh");
+    assert!(c8.take_debug_output().is_empty());
+    assert_eq!(c8.memory[0xF01], b'i');
+}
+
+#[test]
+fn memory_size_is_configurable() {
+    let mut c8 = Chip8::builder()
+        .memory_size(Platform::XoChip.memory_size())
+        .build();
+    assert_eq!(c8.memory().len(), 0x10000);
+    assert_eq!(c8.max_rom_size(), 0x10000 - START);
+    c8.pc = 0x8000;
+    c8.memory[0x8000..0x8002].copy_from_slice(&[0x60, 0x2A]);
+    c8.tick_cpu().unwrap();
+    assert_eq!(c8.registers[0], 0x2A);
+
+    // a state only fits a machine with the same memory
+    let state = c8.save_state();
+    assert_eq!(
+        Chip8::new().load_state(&state),
+        Err(StateError::MemorySizeMismatch)
+    );
+
+    let mut small = Chip8::builder().memory_size(0x300).build();
+    small.pc = 0x2FE;
+    small.memory[0x2FE..0x300].copy_from_slice(&[0x12, 0xFE]);
+    small.tick_cpu().unwrap();
+    small.pc = 0x300;
+    assert_eq!(
+        small.tick_cpu(),
+        Err(Chip8Error::PcOutOfRange { pc: 0x300 })
+    );
+}
+
+#[test]
+#[should_panic]
+fn memory_size_past_64k_panics() {
+    Chip8::builder().memory_size(0x10001);
+}
+
+#[test]
+#[should_panic]
+fn stack_depth_past_the_limit_panics() {
+    Chip8::builder().stack_depth(usize::MAX);
+}
+
+#[test]
+fn roms_are_checked_against_the_memory_size() {
+    assert_eq!(Chip8::check_rom(&[0; 0x600], 0x1000), Ok(()));
+    assert_eq!(
+        Chip8::check_rom(&[0; 0x600], 0x400),
+        Err(LoadError::TooLarge {
+            size: 0x600,
+            max: 0x200
+        })
+    );
+    assert_eq!(Chip8::check_rom(&[0; 0x1000], 0x10000), Ok(()));
+    assert_eq!(Chip8::check_rom(&[], 0x1000), Err(LoadError::Empty));
+}
+
+#[test]
+fn bcd() {
+    let mut c8 = run(&[(5, 234)], 0xA300);
+    c8.exec_opcode(0xF533).unwrap();
+    assert_eq!(&c8.memory[0x300..0x303], &[2, 3, 4]);
+}
+
+#[test]
+fn accesses_are_counted_when_enabled() {
+    assert!(Chip8::new().accesses().is_none());
+
+    let mut c8 = Chip8::builder().count_accesses(true).build();
+    // set I, draw a 2 byte sprite from it, then store V0 and V1 over it
+    c8.load_rom(&[0xA3, 0x00, 0xD0, 0x02, 0xF1, 0x55]).unwrap();
+    for _ in 0..3 {
+        c8.tick_cpu().unwrap();
+    }
+    let accesses = c8.accesses().unwrap();
+    assert_eq!(&accesses.executes[START..START + 7], &[1, 1, 1, 1, 1, 1, 0]);
+    assert_eq!(&accesses.reads[0x300..0x303], &[1, 1, 0]);
+    assert_eq!(&accesses.writes[0x300..0x303], &[1, 1, 0]);
+    assert_eq!(accesses.reads.iter().sum::<u32>(), 2);
+}
+
+#[test]
+fn writes_to_executed_code_are_reported() {
+    let mut c8 = Chip8::builder().count_accesses(true).build();
+    // store over the first instruction, then somewhere that never ran
+    c8.load_rom(&[0xA2, 0x00, 0x60, 0xA2, 0xF0, 0x55, 0xA3, 0x00, 0xF0, 0x55])
+        .unwrap();
+    for _ in 0..5 {
+        c8.tick_cpu().unwrap();
+    }
+    let writes = c8.take_code_writes();
+    assert_eq!(
+        writes,
+        [CodeWrite {
+            pc: 0x204,
+            addr: 0x200,
+            value: 0xA2
+        }]
+    );
+    assert!(c8.take_code_writes().is_empty());
+    let accesses = c8.accesses().unwrap();
+    assert_eq!(accesses.code_writes.iter().sum::<u32>(), 1);
+    assert_eq!(accesses.writes[0x300], 1);
+}
+
+// keeps what is written to 0xE00..0xE10 for itself, and claims every opcode
+struct Storage([u8; 16]);
+
+impl Plugin for Storage {
+    fn claims_opcode(&self, _oc: Opcode) -> bool {
+        true
+    }
+
+    fn execute(&mut self, _oc: Opcode, c8: &mut Chip8) -> Result<(), Chip8Error> {
+        c8.registers_mut()[0xF] = 1;
+        Ok(())
+    }
+
+    fn region(&self) -> Option<Range<usize>> {
+        Some(0xE00..0xE10)
+    }
+
+    fn read(&mut self, addr: usize) -> u8 {
+        self.0[addr - 0xE00] + 1
+    }
+
+    fn write(&mut self, addr: usize, value: u8) {
+        self.0[addr - 0xE00] = value;
+    }
+}
+
+#[test]
+fn plugins_take_unknown_opcodes_and_their_region() {
+    let mut c8 = Chip8::new();
+    c8.add_plugin(Box::new(Storage([0; 16])));
+    // V1 = 7, store V0 and V1 at 0xE00, load them back, then 0x0123
+    c8.load_rom(&[0x61, 0x07, 0xAE, 0x00, 0xF1, 0x55, 0xF1, 0x65, 0x01, 0x23])
+        .unwrap();
+    for _ in 0..4 {
+        c8.tick_cpu().unwrap();
+    }
+    assert_eq!(&c8.registers[0..2], &[1, 8]);
+    assert_eq!(&c8.memory[0xE00..0xE02], &[0, 0]);
+    assert_eq!(c8.registers[0xF], 0);
+
+    c8.tick_cpu().unwrap();
+    assert_eq!(c8.registers[0xF], 1);
+    assert_eq!(c8.pc, START + 10);
+}
+
+#[test]
+fn dump_and_load_registers() {
+    let mut c8 = run(&[(0, 1), (1, 2), (2, 3), (3, 4)], 0xA300);
+    c8.exec_opcode(0xF255).unwrap();
+    assert_eq!(&c8.memory[0x300..0x304], &[1, 2, 3, 0]);
+    assert_eq!(c8.index, 0x300);
+
+    let mut c8 = machine(&[]);
+    c8.memory[0x300..0x303].copy_from_slice(&[9, 8, 7]);
+    c8.exec_opcode(0xA300).unwrap();
+    c8.exec_opcode(0xF265).unwrap();
+    assert_eq!(&c8.registers[0..4], &[9, 8, 7, 0]);
+}
+
+#[test]
+fn load_store_quirk_increments_index() {
+    let mut c8 = with_quirks("load-store-i");
+    c8.exec_opcode(0xA300).unwrap();
+    c8.exec_opcode(0xF255).unwrap();
+    assert_eq!(c8.index, 0x303);
+}
+
+#[test]
+fn key_skips() {
+    let mut c8 = machine(&[(1, 0xA)]);
+    c8.set_key(0xA, true);
+    c8.exec_opcode(0xE19E).unwrap();
+    assert_eq!(c8.pc, START + 4);
+    c8.exec_opcode(0xE1A1).unwrap();
+    assert_eq!(c8.pc, START + 6);
+
+    c8.set_key(0xA, false);
+    c8.exec_opcode(0xE1A1).unwrap();
+    assert_eq!(c8.pc, START + 10);
+}
+
+#[test]
+fn key_skips_use_the_low_nibble_of_vx() {
+    // used to index the keypad with VX = 0x20 and panic
+    let mut c8 = Chip8::builder().build();
+    c8.load_rom(&[0x60, 0x20, 0xE0, 0x9E]).unwrap();
+    c8.tick_cpu().unwrap();
+    c8.tick_cpu().unwrap();
+    assert_eq!(c8.pc, START + 4);
+
+    c8 = machine(&[(1, 0x1A)]);
+    c8.set_key(0x1A, true);
+    assert!(c8.keypad()[0xA]);
+    c8.exec_opcode(0xE19E).unwrap();
+    assert_eq!(c8.pc, START + 4);
+}
+
+#[test]
+fn wait_for_key_blocks_until_pressed() {
+    let mut c8 = machine(&[]);
+    c8.exec_opcode(0xF30A).unwrap();
+    assert_eq!(c8.pc, START);
+
+    c8.set_key(0x7, true);
+    c8.exec_opcode(0xF30A).unwrap();
+    assert_eq!(c8.pc, START + 2);
+    assert_eq!(c8.registers[3], 0x7);
+}
+
+#[test]
+fn timers() {
+    let mut c8 = run(&[(1, 3)], 0xF115);
+    c8.exec_opcode(0xF118).unwrap();
+    c8.tick_60hz();
+    assert_eq!(c8.sound_timer(), 2);
+    c8.exec_opcode(0xF207).unwrap();
+    assert_eq!(c8.registers[2], 2);
+}
+
+#[test]
+fn time_only_passes_when_the_host_ticks() {
+    // V0 = 5, DT = V0, then V1 = DT forever
+    let mut c8 = Chip8::builder().build();
+    c8.load_rom(&[0x60, 0x05, 0xF0, 0x15, 0xF1, 0x07, 0x12, 0x04])
+        .unwrap();
+    for _ in 0..1000 {
+        c8.tick_cpu().unwrap();
+    }
+    assert_eq!(c8.registers[1], 5);
+    for _ in 0..3 {
+        c8.tick_60hz();
+    }
+    c8.tick_cpu().unwrap();
+    assert_eq!(c8.registers[1], 2);
+}
+
+#[test]
+fn random_is_reproducible_with_a_seed() {
+    let randoms = |seed| {
+        let mut c8 = Chip8::builder().seed(seed).build();
+        (0..16)
+            .map(|_| {
+                c8.exec_opcode(0xC0FF).unwrap();
+                c8.registers[0]
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(randoms(42), randoms(42));
+    assert_ne!(randoms(42), randoms(43));
+    assert_eq!(Chip8::builder().seed(42).build().seed(), 42);
+}
+
+#[test]
+fn draw_flips_pixels_and_reports_collision() {
+    // font glyph for 0 is a 4x5 box
+    let mut c8 = run(&[(1, 0), (2, 0)], 0xF129);
+    c8.exec_opcode(0xD125).unwrap();
+    assert!(c8.pixel_buffer()[0][0]);
+    assert!(!c8.pixel_buffer()[1][1]);
+    assert_eq!(c8.registers[0xF], 0);
+    assert!(c8.take_draw_flag());
+
+    c8.exec_opcode(0xD125).unwrap();
+    assert!(!c8.pixel_buffer()[0][0]);
+    assert_eq!(c8.registers[0xF], 1);
+}
+
+#[test]
+fn draw_wraps_unless_clipping() {
+    let mut c8 = run(&[(1, 62), (2, 0), (3, 0)], 0xF329);
+    c8.exec_opcode(0xD125).unwrap();
+    assert!(c8.pixel_buffer()[0][1]);
+
+    let mut c8 = with_quirks("clip");
+    c8.registers[1] = 62;
+    c8.exec_opcode(0xF329).unwrap();
+    c8.exec_opcode(0xD125).unwrap();
+    assert!(c8.pixel_buffer()[0][63]);
+    assert!(!c8.pixel_buffer()[0][1]);
+}
+
+#[test]
+fn clear_screen() {
+    let mut c8 = run(&[], 0xF029);
+    c8.exec_opcode(0xD005).unwrap();
+    c8.exec_opcode(0x00E0).unwrap();
+    assert!(c8.pixel_buffer().iter().flatten().all(|&p| !p));
+}
+
+#[test]
+fn index_past_end_of_memory_follows_policy() {
+    let machine = |policy| {
+        let mut c8 = Chip8::builder().memory_policy(policy).build();
+        c8.registers[0] = 0xFF;
+        c8.exec_opcode(0xAFFF).unwrap();
+        c8.exec_opcode(0xF01E).unwrap();
+        c8.registers[0] = 123;
+        c8
+    };
+
+    let mut c8 = machine(MemoryPolicy::Wrap);
+    c8.exec_opcode(0xF033).unwrap();
+    assert_eq!(&c8.memory[0x0FE..0x101], &[1, 2, 3]);
+
+    let mut c8 = machine(MemoryPolicy::Clamp);
+    c8.exec_opcode(0xF033).unwrap();
+    assert_eq!(c8.memory[0xFFF], 3);
+
+    let mut c8 = machine(MemoryPolicy::Error);
+    assert_eq!(
+        c8.exec_opcode(0xF033),
+        Err(Chip8Error::MemoryOutOfRange {
+            pc: START + 4,
+            addr: 0x10FE
+        })
+    );
+}
+
+#[test]
+fn writes_below_0x200_follow_write_protection() {
+    // FX55 with I on the font, storing V0 = 0xAA over the top of the 0
+    let machine = |protection| {
+        let mut c8 = Chip8::builder().write_protection(protection).build();
+        c8.registers[0] = 0xAA;
+        c8.exec_opcode(0xA050).unwrap();
+        c8
+    };
+
+    let mut c8 = machine(WriteProtection::Off);
+    c8.exec_opcode(0xF055).unwrap();
+    assert_eq!(c8.memory[0x050], 0xAA);
+
+    let mut c8 = machine(WriteProtection::Warn);
+    c8.exec_opcode(0xF055).unwrap();
+    assert_eq!(c8.memory[0x050], 0xF0);
+
+    let mut c8 = machine(WriteProtection::Strict);
+    assert_eq!(
+        c8.exec_opcode(0xF055),
+        Err(Chip8Error::ProtectedWrite {
+            pc: START + 2,
+            addr: 0x050
+        })
+    );
+    assert_eq!(c8.memory[0x050], 0xF0);
+
+    // writes from 0x200 up are not affected
+    c8.exec_opcode(0xA200).unwrap();
+    c8.exec_opcode(0xF055).unwrap();
+    assert_eq!(c8.memory[0x200], 0xAA);
+}
+
+#[test]
+fn platforms_parse_and_pick_quirks() {
+    for name in &["vip", "schip", "xo-chip"] {
+        assert_eq!(name.parse::<Platform>().unwrap().to_string(), *name);
+    }
+    assert!("dream".parse::<Platform>().is_err());
+    assert_eq!(
+        Platform::Vip.quirks(),
+        "shift-vy,load-store-i,vf-reset,clip".parse().unwrap()
+    );
+
+    let mut quirks = Platform::Schip.quirks();
+    quirks.set("clip", false).unwrap();
+    assert_eq!(quirks, "jump-vx".parse().unwrap());
+    assert!(quirks.set("wrap", true).is_err());
+    assert_eq!(quirks.get("jump-vx"), Ok(true));
+    assert_eq!(quirks.get("clip"), Ok(false));
+    assert!(quirks.get("wrap").is_err());
+}
+
+#[test]
+fn rom_size_is_validated() {
+    let mut c8 = machine(&[]);
+    assert_eq!(c8.load_rom(&[]), Err(LoadError::Empty));
+    assert_eq!(
+        c8.load_rom(&[0; 3585]),
+        Err(LoadError::TooLarge {
+            size: 3585,
+            max: 3584
+        })
+    );
+    c8.load_rom(&[0xAB; 3584]).unwrap();
+    assert_eq!(c8.memory[0xFFF], 0xAB);
+}
+
+#[test]
+fn rom_ids_match_reference_hashes() {
+    let id = RomId::of(b"abc");
+    assert_eq!(
+        id.to_string(),
+        "sha1 a9993e364706816aba3e25717850c26c9cd0d89d crc32 352441c2 (3 bytes)"
+    );
+    // padding spills into a second block
+    let id = RomId::of(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+    assert!(id
+        .to_string()
+        .starts_with("sha1 84983e441c3bd26ebaae4aa1f95129e5e54670f1"));
+    assert_eq!(RomId::of(b"123456789").crc32, 0xCBF4_3926);
+}
+
+#[test]
+fn states_only_load_into_the_same_rom() {
+    let with_rom = |rom: &[u8]| {
+        let mut c8 = Chip8::new();
+        c8.load_rom(rom).unwrap();
+        c8
+    };
+    let saved = with_rom(&[0x60, 0x01]).save_state();
+    with_rom(&[0x60, 0x01]).load_state(&saved).unwrap();
+    assert_eq!(
+        with_rom(&[0x60, 0x02]).load_state(&saved),
+        Err(StateError::WrongRom)
+    );
+
+    // a machine without a ROM takes the one from the state
+    let mut c8 = Chip8::new();
+    c8.load_state(&saved).unwrap();
+    assert_eq!(c8.rom_id(), Some(&RomId::of(&[0x60, 0x01])));
+}
+
+#[test]
+fn states_keep_an_index_past_0xffff() {
+    let new = || Chip8::builder().memory_size(MAX_MEMORY_SIZE).build();
+    let mut c8 = new();
+    c8.index = 0xFFFF;
+    c8.registers[0] = 0x20;
+    c8.memory[START..START + 2].copy_from_slice(&[0xF0, 0x1E]);
+    c8.tick_cpu().unwrap();
+    assert_eq!(c8.index(), 0x1001F);
+
+    let saved = c8.save_state();
+    let mut restored = new();
+    restored.load_state(&saved).unwrap();
+    assert_eq!(restored.index(), 0x1001F);
+    assert_eq!(restored.save_state(), saved);
+}
+
+#[test]
+fn states_carry_the_random_number_generator() {
+    // CXNN with V0 = rand & 0xFF, over and over
+    let rom = [0xC0, 0xFF, 0x12, 0x00];
+    let mut c8 = Chip8::builder().seed(7).build();
+    c8.load_rom(&rom).unwrap();
+    for _ in 0..10 {
+        c8.tick_cpu().unwrap();
+    }
+    let saved = c8.save_state();
+
+    let mut restored = Chip8::builder().seed(8).build();
+    restored.load_rom(&rom).unwrap();
+    restored.load_state(&saved).unwrap();
+    assert_eq!(restored.seed(), 7);
+    for _ in 0..10 {
+        c8.tick_cpu().unwrap();
+        restored.tick_cpu().unwrap();
+        assert_eq!(restored.registers[0], c8.registers[0]);
+    }
+}
+
+// the generator is written out so its state can be saved, but seeds have to
+// keep drawing what they did with SmallRng
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn random_numbers_match_small_rng() {
+    use rand::{Rng as _, SeedableRng};
+    for seed in [0, 1, 42, u64::MAX] {
+        let mut ours = crate::rng::Rng::seed_from_u64(seed);
+        let mut small = rand::rngs::SmallRng::seed_from_u64(seed);
+        for _ in 0..100 {
+            assert_eq!(ours.next_byte(), small.gen::<u8>());
+        }
+    }
+}
+
+#[test]
+fn agent_runs_whole_frames() {
+    let mut c8 = Chip8::new();
+    // V0 += 1, then jump back to it
+    c8.load_rom(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+    c8.sound_timer = 5;
+    let mut agent = Agent::new(c8, 10);
+    agent.advance(2).unwrap();
+    // each frame runs 10 instructions, half of them the add
+    assert_eq!(agent.machine().registers[0], 10);
+    assert_eq!(agent.machine().sound_timer(), 3);
+
+    agent.set_keys(0b1000_0000_0000_0011);
+    assert_eq!(agent.keys(), 0b1000_0000_0000_0011);
+    assert!(agent.machine().keypad()[0xF]);
+
+    agent.machine_mut().pixel_buffer[0][0] = true;
+    agent.machine_mut().pixel_buffer[1][9] = true;
+    let screen = agent.screen();
+    assert_eq!(screen[0], 0x80);
+    assert_eq!(screen[9], 0x40);
+}
+
+#[test]
+fn quirk_combinations_cover_every_set() {
+    let all: Vec<Quirks> = Quirks::combinations().collect();
+    assert_eq!(all.len(), 1 << Quirks::names().len());
+    assert_eq!(all[0], Quirks::default());
+    for (i, quirks) in all.iter().enumerate() {
+        assert!(!all[..i].contains(quirks));
+    }
+}
@@ -0,0 +1,453 @@
+//! libretro wrapper around `chip8-core`, lets the emulator run inside RetroArch
+//! or any other libretro frontend.
+
+use chip8_core::{Chip8, LoadError, DEFAULT_SPEED, HEIGHT, WIDTH};
+use libc::{c_char, c_uint, c_void, size_t};
+use libretro_sys::*;
+use log::{error, Level, LevelFilter, Log, Metadata, Record};
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::sync::{Mutex, MutexGuard};
+use std::{ptr, slice};
+
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: f64 = 44100.0;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / FPS) as usize;
+const TONE_HZ: f64 = 440.0;
+const VOLUME: i16 = 0x1000;
+
+const FOREGROUND: u32 = 0x00FF_FFFF;
+const BACKGROUND: u32 = 0x0000_0000;
+
+// RetroPad buttons and keyboard keys, indexed by keypad value. The keyboard
+// layout is the same as the one used by the SDL frontend.
+const JOYPAD_MAP: [(c_uint, usize); 10] = [
+    (DEVICE_ID_JOYPAD_UP, 0x2),
+    (DEVICE_ID_JOYPAD_DOWN, 0x8),
+    (DEVICE_ID_JOYPAD_LEFT, 0x4),
+    (DEVICE_ID_JOYPAD_RIGHT, 0x6),
+    (DEVICE_ID_JOYPAD_A, 0x5),
+    (DEVICE_ID_JOYPAD_B, 0x0),
+    (DEVICE_ID_JOYPAD_X, 0x1),
+    (DEVICE_ID_JOYPAD_Y, 0x3),
+    (DEVICE_ID_JOYPAD_START, 0xf),
+    (DEVICE_ID_JOYPAD_SELECT, 0xe),
+];
+
+const KEYBOARD_MAP: [(Key, usize); 16] = [
+    (Key::Number_1, 0x1),
+    (Key::Number_2, 0x2),
+    (Key::Number_3, 0x3),
+    (Key::Q, 0x4),
+    (Key::W, 0x5),
+    (Key::E, 0x6),
+    (Key::A, 0x7),
+    (Key::S, 0x8),
+    (Key::D, 0x9),
+    (Key::X, 0x0),
+    (Key::Z, 0xa),
+    (Key::C, 0xb),
+    (Key::Number_4, 0xc),
+    (Key::R, 0xd),
+    (Key::F, 0xe),
+    (Key::V, 0xf),
+];
+
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+struct Core {
+    c8: Chip8,
+    rom: Vec<u8>,
+    frame: Vec<u32>,
+    audio: Vec<i16>,
+    phase: f64,
+    // set when the program hits an error, until the next reset or state load
+    halted: bool,
+}
+
+struct State {
+    callbacks: Callbacks,
+    core: Option<Core>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    callbacks: Callbacks {
+        environment: None,
+        video_refresh: None,
+        audio_sample_batch: None,
+        input_poll: None,
+        input_state: None,
+    },
+    core: None,
+});
+
+fn state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// the frontend's log interface, kept apart from STATE since records are
+// logged while STATE is locked
+static LOG: Mutex<Option<LogPrintfFn>> = Mutex::new(None);
+
+// sends log records to the frontend, a cdylib has no terminal of its own
+struct RetroLogger;
+
+impl Log for RetroLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        let log = match *LOG.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(log) if self.enabled(record.metadata()) => log,
+            _ => return,
+        };
+        let level = match record.level() {
+            Level::Error => LogLevel::Error,
+            Level::Warn => LogLevel::Warn,
+            Level::Info => LogLevel::Info,
+            _ => LogLevel::Debug,
+        };
+        // the message is the format string, so a % in it has to be escaped
+        let text = format!("chip8: {}\n", record.args()).replace('%', "%%");
+        if let Ok(text) = CString::new(text) {
+            unsafe { log(level, text.as_ptr()) };
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: RetroLogger = RetroLogger;
+
+impl Core {
+    fn new(rom: Vec<u8>) -> Result<Self, LoadError> {
+        let mut c8 = Chip8::new();
+        c8.load_rom(&rom)?;
+        Ok(Core {
+            c8,
+            rom,
+            frame: vec![BACKGROUND; WIDTH * HEIGHT],
+            audio: vec![0; SAMPLES_PER_FRAME * 2],
+            phase: 0.0,
+            halted: false,
+        })
+    }
+
+    fn reset(&mut self) {
+        self.c8 = Chip8::new();
+        self.c8
+            .load_rom(&self.rom)
+            .expect("the ROM was checked when it was loaded");
+        self.halted = false;
+    }
+
+    fn update_frame(&mut self) {
+        for (y, row) in self.c8.pixel_buffer().iter().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                self.frame[y * WIDTH + x] = if pixel { FOREGROUND } else { BACKGROUND };
+            }
+        }
+    }
+
+    fn fill_audio(&mut self) {
+        let beeping = self.c8.sound_timer() > 0;
+        for frame in self.audio.chunks_mut(2) {
+            let sample = if !beeping {
+                0
+            } else if self.phase < 0.5 {
+                VOLUME
+            } else {
+                -VOLUME
+            };
+            frame[0] = sample;
+            frame[1] = sample;
+            self.phase = (self.phase + TONE_HZ / SAMPLE_RATE) % 1.0;
+        }
+    }
+}
+
+unsafe fn poll_input(callbacks: &Callbacks, c8: &mut Chip8) {
+    let (poll, input_state) = match (callbacks.input_poll, callbacks.input_state) {
+        (Some(poll), Some(input_state)) => (poll, input_state),
+        _ => return,
+    };
+    poll();
+
+    let mut keypad = [false; 16];
+    for &(id, key) in JOYPAD_MAP.iter() {
+        keypad[key] |= input_state(0, DEVICE_JOYPAD, 0, id) != 0;
+    }
+    for &(id, key) in KEYBOARD_MAP.iter() {
+        keypad[key] |= input_state(0, DEVICE_KEYBOARD, 0, id as c_uint) != 0;
+    }
+    for (key, &pressed) in keypad.iter().enumerate() {
+        c8.set_key(key, pressed);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    state().callbacks.environment = Some(callback);
+
+    let mut interface = MaybeUninit::<LogCallback>::uninit();
+    let found = unsafe {
+        callback(
+            ENVIRONMENT_GET_LOG_INTERFACE,
+            interface.as_mut_ptr() as *mut c_void,
+        )
+    };
+    if found {
+        *LOG.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(unsafe { interface.assume_init() }.log);
+        // fails when called again, the logger is already in place then
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(LevelFilter::Info);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    state().callbacks.video_refresh = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    state().callbacks.audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    state().callbacks.input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    state().callbacks.input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    state().core = None;
+}
+
+/// # Safety
+///
+/// `info` must point to a valid `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    *info = SystemInfo {
+        library_name: b"chip8-rs\0".as_ptr() as *const c_char,
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: b"ch8|c8|rom\0".as_ptr() as *const c_char,
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+///
+/// `info` must point to a valid `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    *info = SystemAvInfo {
+        geometry: GameGeometry {
+            base_width: WIDTH as c_uint,
+            base_height: HEIGHT as c_uint,
+            max_width: WIDTH as c_uint,
+            max_height: HEIGHT as c_uint,
+            aspect_ratio: (WIDTH as f32) / (HEIGHT as f32),
+        },
+        timing: SystemTiming {
+            fps: FPS,
+            sample_rate: SAMPLE_RATE,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = state().core.as_mut() {
+        core.reset();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let mut guard = state();
+    let state = &mut *guard;
+    let core = match state.core.as_mut() {
+        Some(core) => core,
+        None => return,
+    };
+
+    unsafe { poll_input(&state.callbacks, &mut core.c8) };
+    for _ in 0..DEFAULT_SPEED {
+        if core.halted {
+            break;
+        }
+        if let Err(e) = core.c8.tick_cpu() {
+            error!("{}", e);
+            core.halted = true;
+        }
+    }
+    core.c8.tick_60hz();
+    core.c8.take_draw_flag();
+
+    core.update_frame();
+    core.fill_audio();
+
+    if let Some(video_refresh) = state.callbacks.video_refresh {
+        unsafe {
+            video_refresh(
+                core.frame.as_ptr() as *const c_void,
+                WIDTH as c_uint,
+                HEIGHT as c_uint,
+                WIDTH * 4,
+            )
+        };
+    }
+    if let Some(audio_sample_batch) = state.callbacks.audio_sample_batch {
+        unsafe { audio_sample_batch(core.audio.as_ptr(), SAMPLES_PER_FRAME) };
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> size_t {
+    state()
+        .core
+        .as_ref()
+        .map_or(0, |core| core.c8.save_state().len())
+}
+
+/// # Safety
+///
+/// `data` must point to at least `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: size_t) -> bool {
+    let guard = state();
+    let core = match guard.core.as_ref() {
+        Some(core) => core,
+        None => return false,
+    };
+    let saved = core.c8.save_state();
+    if saved.len() > size {
+        return false;
+    }
+    ptr::copy_nonoverlapping(saved.as_ptr(), data as *mut u8, saved.len());
+    true
+}
+
+/// # Safety
+///
+/// `data` must point to at least `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: size_t) -> bool {
+    let mut guard = state();
+    let core = match guard.core.as_mut() {
+        Some(core) => core,
+        None => return false,
+    };
+    let data = slice::from_raw_parts(data as *const u8, size);
+    if core.c8.load_state(data).is_err() {
+        return false;
+    }
+    core.halted = false;
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+///
+/// `game` must be null or point to a valid `retro_game_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+    let rom = slice::from_raw_parts((*game).data as *const u8, (*game).size).to_vec();
+    let core = match Core::new(rom) {
+        Ok(core) => core,
+        Err(_) => return false,
+    };
+
+    let mut guard = state();
+    if let Some(environment) = guard.callbacks.environment {
+        let mut format = PixelFormat::ARGB8888;
+        if !environment(
+            ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut PixelFormat as *mut c_void,
+        ) {
+            return false;
+        }
+    }
+    guard.core = Some(core);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const GameInfo,
+    _num_info: size_t,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    state().core = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    0 // RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    if id != MEMORY_SYSTEM_RAM {
+        return ptr::null_mut();
+    }
+    match state().core.as_mut() {
+        Some(core) => core.c8.memory_mut().as_mut_ptr() as *mut c_void,
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> size_t {
+    if id != MEMORY_SYSTEM_RAM {
+        return 0;
+    }
+    state()
+        .core
+        .as_ref()
+        .map_or(0, |core| core.c8.memory().len())
+}
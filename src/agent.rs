@@ -0,0 +1,154 @@
+// `agent`: serves a headless machine over TCP for bots written in any
+// language. One client at a time sends text commands, one per line, and gets
+// one line back, starting with `ok` or `error`:
+//
+//   keys <hex>          hold exactly these keys, bit n is keypad key n
+//   step [<frames>]     run that many 60Hz frames, 1 by default, answers with
+//                       the frame count
+//   screen              the screen as 512 hex digits, one bit per pixel, row
+//                       major, most significant bit first
+//   regs                V0 to VF, I, pc, delay and sound timer in hex
+//   peek <addr> [<n>]   n bytes of memory from addr, in hex
+//   poke <addr> <hex>   write bytes starting at addr
+//   reset               reload the ROM and start from frame 0
+//
+// e.g. `printf 'keys 20\nstep 60\nscreen\n' | nc 127.0.0.1 9000`
+use crate::cli::AgentArgs;
+use chip8_core::{Agent, Chip8};
+use log::{error, info};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::exit;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex(s: Option<&str>, what: &str) -> Result<usize, String> {
+    let s = s.ok_or(format!("missing {}", what))?;
+    usize::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("bad {} `{}`", what, s))
+}
+
+fn new_agent(args: &AgentArgs, data: &[u8]) -> Agent {
+    let mut c8 = Chip8::builder().quirks(args.quirks).seed(args.seed).build();
+    if let Err(e) = c8.load_rom(data) {
+        error!("{}: {}", args.rom.display(), e);
+        exit(crate::rom::EXIT_INVALID);
+    }
+    Agent::new(c8, args.speed)
+}
+
+fn command(line: &str, agent: &mut Agent, reset: impl Fn() -> Agent) -> Result<String, String> {
+    let mut words = line.split_whitespace();
+    let reply = match words.next() {
+        Some("keys") => {
+            agent.set_keys(parse_hex(words.next(), "keys")? as u16);
+            String::new()
+        }
+        Some("step") => {
+            let frames = match words.next() {
+                Some(n) => n.parse().map_err(|_| format!("bad frame count `{}`", n))?,
+                None => 1,
+            };
+            agent.advance(frames).map_err(|e| e.to_string())?;
+            agent.frames().to_string()
+        }
+        Some("screen") => hex(&agent.screen()),
+        Some("regs") => {
+            let c8 = agent.machine();
+            format!(
+                "{} {:03x} {:03x} {:02x} {:02x}",
+                hex(c8.registers()),
+                c8.index(),
+                c8.pc(),
+                c8.delay_timer(),
+                c8.sound_timer()
+            )
+        }
+        Some("peek") => {
+            let addr = parse_hex(words.next(), "address")?;
+            let len = words.next().map_or(Ok(1), |n| {
+                n.parse().map_err(|_| format!("bad length `{}`", n))
+            })?;
+            let memory = agent.machine().memory();
+            hex(memory
+                .get(addr..addr.saturating_add(len))
+                .ok_or("past the end of memory")?)
+        }
+        Some("poke") => {
+            let addr = parse_hex(words.next(), "address")?;
+            let data = words.next().ok_or("missing bytes")?;
+            let bytes = (0..data.len())
+                .step_by(2)
+                .map(|i| {
+                    data.get(i..i + 2)
+                        .and_then(|b| u8::from_str_radix(b, 16).ok())
+                })
+                .collect::<Option<Vec<u8>>>()
+                .ok_or(format!("bad bytes `{}`", data))?;
+            agent
+                .machine_mut()
+                .memory_mut()
+                .get_mut(addr..addr.saturating_add(bytes.len()))
+                .ok_or("past the end of memory")?
+                .copy_from_slice(&bytes);
+            String::new()
+        }
+        Some("reset") => {
+            *agent = reset();
+            String::new()
+        }
+        Some(other) => return Err(format!("unknown command `{}`", other)),
+        None => return Err("empty command".to_string()),
+    };
+    Ok(reply)
+}
+
+fn serve_client(
+    stream: TcpStream,
+    agent: &mut Agent,
+    reset: &impl Fn() -> Agent,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let reply = match command(&line?, agent, reset) {
+            Ok(reply) if reply.is_empty() => "ok".to_string(),
+            Ok(reply) => format!("ok {}", reply),
+            Err(e) => format!("error {}", e),
+        };
+        writeln!(writer, "{}", reply)?;
+    }
+    Ok(())
+}
+
+pub fn run(args: AgentArgs) {
+    let data = crate::rom::read_or_exit(&args.rom);
+    let reset = || new_agent(&args, &data);
+    let mut agent = reset();
+
+    let listener = TcpListener::bind(&args.listen).unwrap_or_else(|e| {
+        error!("cannot listen on {}: {}", args.listen, e);
+        exit(1);
+    });
+    info!("agent API on {}", args.listen);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("cannot accept a connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+        info!("{} connected", peer);
+        // the machine carries on where the last client left it
+        if let Err(e) = serve_client(stream, &mut agent, &reset) {
+            error!("{}: {}", peer, e);
+        }
+        info!("{} disconnected", peer);
+    }
+}
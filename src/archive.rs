@@ -0,0 +1,99 @@
+// Compressed ROMs. A `.gz` file holds a single ROM. A `.zip` can hold many, so
+// a ROM inside one is named by a path through the archive, `games.zip/PONG.ch8`,
+// which is what the picker, playlists and the recent list store. A zip with a
+// single ROM in it can be opened as it is.
+use chip8_core::MAX_MEMORY_SIZE;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// extensions of the entries taken for ROMs, anything else in an archive (read
+// me files, screenshots) is skipped
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "rom", "o8"];
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
+pub fn is_zip(path: &Path) -> bool {
+    has_extension(path, "zip")
+}
+
+pub fn is_gzip(path: &Path) -> bool {
+    has_extension(path, "gz")
+}
+
+/// Whether `path` looks like something that can be run: a ROM, an Octo source
+/// or an archive of them. Files without an extension are taken for ROMs, as
+/// most of the classic packs name them.
+pub fn is_loadable(path: &Path) -> bool {
+    path.extension().is_none()
+        || is_zip(path)
+        || is_gzip(path)
+        || ROM_EXTENSIONS.iter().any(|ext| has_extension(path, ext))
+}
+
+/// The zip archive and the entry in it when `path` points inside one.
+pub fn split(path: &Path) -> Option<(PathBuf, String)> {
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| is_zip(dir) && dir.is_file())?;
+    let entry = path.strip_prefix(archive).ok()?;
+    let names: Vec<_> = entry.iter().map(|part| part.to_string_lossy()).collect();
+    Some((archive.to_path_buf(), names.join("/")))
+}
+
+fn open(archive: &Path) -> Result<zip::ZipArchive<File>, String> {
+    let file = File::open(archive).map_err(|e| e.to_string())?;
+    zip::ZipArchive::new(file).map_err(|e| e.to_string())
+}
+
+/// The names of the ROMs in a zip archive, sorted.
+pub fn roms(archive: &Path) -> Result<Vec<String>, String> {
+    let mut zip = open(archive)?;
+    let mut roms = Vec::new();
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i).map_err(|e| e.to_string())?;
+        let name = entry.name().to_string();
+        let is_rom = ROM_EXTENSIONS
+            .iter()
+            .any(|ext| has_extension(Path::new(&name), ext));
+        if entry.is_file() && is_rom {
+            roms.push(name);
+        }
+    }
+    roms.sort();
+    Ok(roms)
+}
+
+// Unpack at most a little more than the largest memory, so a small file that
+// inflates without end cannot use up all of ours
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    reader
+        .take(MAX_MEMORY_SIZE as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| e.to_string())?;
+    if data.len() > MAX_MEMORY_SIZE {
+        return Err(format!(
+            "unpacks to more than {} bytes, too big for a ROM",
+            MAX_MEMORY_SIZE
+        ));
+    }
+    Ok(data)
+}
+
+/// Unpack one entry of a zip archive.
+pub fn read_entry(archive: &Path, name: &str) -> Result<Vec<u8>, String> {
+    let mut zip = open(archive)?;
+    let entry = zip.by_name(name).map_err(|e| format!("{}: {}", name, e))?;
+    read_limited(entry).map_err(|e| format!("{}: {}", name, e))
+}
+
+/// Unpack a gzip file.
+pub fn gunzip(path: &Path) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    read_limited(flate2::read::GzDecoder::new(file))
+}
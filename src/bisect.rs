@@ -0,0 +1,133 @@
+// `bisect-quirks`: runs a ROM under every combination of quirks, optionally
+// feeding it an input movie, and groups the combinations by the screens they
+// produce at a few checkpoints. Combinations in the same group are
+// indistinguishable for this run, and a quirk that splits groups when flipped
+// on its own is one the game actually depends on.
+use crate::cli::BisectArgs;
+use crate::movie::Movie;
+use chip8_core::{Agent, Chip8, Chip8Error, Quirks, RomId, DEFAULT_SPEED};
+use log::{error, warn};
+use std::collections::BTreeMap;
+use std::process::exit;
+
+// the screen hash at each checkpoint, or where the run crashed
+type Outcome = Result<Vec<u64>, (u64, Chip8Error)>;
+
+fn run_one(
+    data: &[u8],
+    quirks: Quirks,
+    movie: Option<&Movie>,
+    speed: u32,
+    seed: u64,
+    checkpoints: &[u64],
+) -> Outcome {
+    let mut c8 = Chip8::builder().quirks(quirks).seed(seed).build();
+    // the ROM was checked when the first combination ran
+    c8.load_rom(data).unwrap();
+    let mut agent = Agent::new(c8, speed);
+    let mut hashes = Vec::new();
+    for &checkpoint in checkpoints {
+        while agent.frames() < checkpoint {
+            if let Some(movie) = movie {
+                movie.apply(&mut agent);
+            }
+            agent.advance(1).map_err(|e| (agent.frames(), e))?;
+        }
+        hashes.push(agent.machine().framebuffer_hash());
+    }
+    Ok(hashes)
+}
+
+fn describe(outcome: &Outcome) -> String {
+    match outcome {
+        Ok(hashes) => hashes
+            .iter()
+            .map(|hash| format!("{:016x}", hash))
+            .collect::<Vec<_>>()
+            .join(" "),
+        Err((frame, e)) => format!("crashed at frame {}: {}", frame, e),
+    }
+}
+
+pub fn run(args: BisectArgs) {
+    let data = crate::rom::read_or_exit(&args.rom);
+    if let Err(e) = Chip8::new().load_rom(&data) {
+        error!("{}: {}", args.rom.display(), e);
+        exit(crate::rom::EXIT_INVALID);
+    }
+    let movie = args.movie.as_deref().map(|path| {
+        let movie = Movie::load(path).unwrap_or_else(|e| {
+            error!("{}", e);
+            exit(1);
+        });
+        if movie.rom != RomId::of(&data).sha1_hex() {
+            warn!("{} was recorded with a different ROM", path.display());
+        }
+        movie
+    });
+    let speed = args
+        .speed
+        .or(movie.as_ref().map(|m| m.speed))
+        .unwrap_or(DEFAULT_SPEED);
+    let seed = args.seed.or(movie.as_ref().map(|m| m.seed)).unwrap_or(0);
+
+    let mut checkpoints = args.at.clone();
+    if checkpoints.is_empty() {
+        checkpoints.push(movie.as_ref().map_or(600, Movie::frames));
+    }
+    checkpoints.sort_unstable();
+    checkpoints.dedup();
+
+    let outcomes: BTreeMap<String, Vec<Quirks>> =
+        Quirks::combinations().fold(BTreeMap::new(), |mut groups, quirks| {
+            let outcome = run_one(&data, quirks, movie.as_ref(), speed, seed, &checkpoints);
+            groups
+                .entry(describe(&outcome))
+                .or_insert_with(Vec::new)
+                .push(quirks);
+            groups
+        });
+
+    let frames: Vec<String> = checkpoints.iter().map(u64::to_string).collect();
+    println!(
+        "screens at frame {}, speed {}, seed {}",
+        frames.join(", "),
+        speed,
+        seed
+    );
+    println!(
+        "{} distinct results from {} quirk combinations",
+        outcomes.len(),
+        1 << Quirks::names().len()
+    );
+    for (result, configs) in &outcomes {
+        println!("  {}", result);
+        for quirks in configs {
+            println!("    {}", quirks);
+        }
+    }
+
+    // a quirk matters if flipping just that one moves some combination to
+    // another group
+    let group_of = |quirks: &Quirks| {
+        outcomes
+            .iter()
+            .position(|(_, configs)| configs.contains(quirks))
+    };
+    let relevant: Vec<&str> = Quirks::names()
+        .iter()
+        .copied()
+        .filter(|name| {
+            Quirks::combinations().any(|quirks| {
+                let mut flipped = quirks;
+                flipped.set(name, !quirks.get(name).unwrap()).unwrap();
+                group_of(&quirks) != group_of(&flipped)
+            })
+        })
+        .collect();
+    if relevant.is_empty() {
+        println!("no quirk changes the result");
+    } else {
+        println!("quirks that change the result: {}", relevant.join(", "));
+    }
+}
@@ -0,0 +1,123 @@
+// Cheat files, one poke per line:
+//
+//   <addr> <value> <freeze|once>
+//
+// with the address and value in hex, e.g. `2f5 09 freeze` to keep a lives
+// counter at 9. `once` cheats are written when the ROM is loaded or reset,
+// `freeze` cheats are written again after every frame so the game can't
+// change them. Blank lines and lines starting with `#` are ignored.
+//
+// The file for a ROM is looked up as cheats/<rom name>.cht in the data
+// directory, or given with --cheats.
+use chip8_core::Chip8;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Freeze,
+    Once,
+}
+
+struct Cheat {
+    addr: usize,
+    value: u8,
+    mode: Mode,
+}
+
+#[derive(Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+    usize::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+}
+
+fn parse_cheat(line: &str, memory_len: usize) -> Result<Cheat, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 3 {
+        return Err("expected <addr> <value> <freeze|once>".to_string());
+    }
+    let addr = parse_hex(fields[0])
+        .filter(|&addr| addr < memory_len)
+        .ok_or_else(|| format!("bad address `{}`", fields[0]))?;
+    let value = parse_hex(fields[1])
+        .filter(|&value| value <= 0xFF)
+        .ok_or_else(|| format!("bad value `{}`", fields[1]))?;
+    let mode = match fields[2] {
+        "freeze" => Mode::Freeze,
+        "once" => Mode::Once,
+        other => return Err(format!("`{}` is not freeze or once", other)),
+    };
+    Ok(Cheat {
+        addr,
+        value: value as u8,
+        mode,
+    })
+}
+
+impl Cheats {
+    pub fn parse(text: &str, memory_len: usize) -> Result<Cheats, String> {
+        let mut cheats = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            cheats
+                .push(parse_cheat(line, memory_len).map_err(|e| format!("line {}: {}", i + 1, e))?);
+        }
+        Ok(Cheats { cheats })
+    }
+
+    /// Read the cheats in `path`. A missing file is only an error when it was
+    /// asked for explicitly, otherwise there are no cheats.
+    pub fn load(path: &Path, explicit: bool, memory_len: usize) -> Result<Cheats, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) if !explicit && !path.exists() => return Ok(Cheats::default()),
+            Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+        };
+        Cheats::parse(&text, memory_len).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Add the cheat written as a cheat file line.
+    pub fn add(&mut self, line: &str, memory_len: usize) -> Result<(), String> {
+        self.cheats.push(parse_cheat(line, memory_len)?);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    fn poke(&self, c8: &mut Chip8, only_frozen: bool) {
+        for cheat in &self.cheats {
+            if !only_frozen || cheat.mode == Mode::Freeze {
+                c8.memory_mut()[cheat.addr] = cheat.value;
+            }
+        }
+    }
+
+    /// Write every cheat, for a freshly loaded machine.
+    pub fn apply(&self, c8: &mut Chip8) {
+        self.poke(c8, false);
+    }
+
+    /// Write the cheat added last, as it is switched on mid game.
+    pub fn apply_last(&self, c8: &mut Chip8) {
+        if let Some(cheat) = self.cheats.last() {
+            c8.memory_mut()[cheat.addr] = cheat.value;
+        }
+    }
+
+    /// Write the frozen cheats again, after a frame has run.
+    pub fn refresh(&self, c8: &mut Chip8) {
+        self.poke(c8, true);
+    }
+}
@@ -0,0 +1,456 @@
+use crate::config::{self, Palette};
+use crate::persist::MemoryRange;
+use chip8_core::{Font, MemoryPolicy, Platform, Quirks, WriteProtection, DEFAULT_SPEED};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Chip-8 emulator",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // `chip8-rs ROM` is shorthand for `chip8-rs run ROM`
+    #[command(flatten)]
+    pub run: RunArgs,
+
+    /// Log more, repeat for more detail (-v debug, -vv every instruction).
+    /// RUST_LOG works as well
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a ROM (the default when no subcommand is given)
+    Run(Box<RunArgs>),
+    /// Run the test suite ROMs headlessly and report which ones pass
+    Conformance(ConformanceArgs),
+    /// Run a ROM headlessly for a number of cycles and check the final screen
+    Verify(VerifyArgs),
+    /// Run a ROM against a per-instruction trace from another emulator and
+    /// stop at the first difference
+    Trace(TraceArgs),
+    /// Run a ROM under every combination of quirks and report which ones
+    /// give different screens
+    BisectQuirks(BisectArgs),
+    /// Serve a headless machine over TCP for bots to play, see src/agent.rs
+    Agent(AgentArgs),
+    /// Run a ROM or Octo source (.o8) and reload it every time the file is
+    /// saved
+    Watch(Box<WatchArgs>),
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// ROM to run, or - to read it from stdin. A picker is shown when
+    /// omitted
+    pub rom: Option<PathBuf>,
+
+    /// Reopen the most recently played ROM
+    #[arg(long, conflicts_with = "rom")]
+    pub recent: bool,
+
+    /// Play these ROMs (or all ROMs in these directories) one after another,
+    /// moving on with the next hotkey (F6) or after --playlist-interval
+    #[arg(long, num_args = 1.., value_name = "PATH", conflicts_with_all = ["rom", "recent", "host", "join", "serve"])]
+    pub playlist: Vec<PathBuf>,
+
+    /// Move on to the next ROM of the playlist after this many seconds
+    #[arg(long, value_name = "SECS", requires = "playlist")]
+    pub playlist_interval: Option<u64>,
+
+    /// Directory scanned for ROMs when no ROM is given
+    #[arg(long, env = "CHIP8_ROM_DIR", default_value = "roms")]
+    pub rom_dir: PathBuf,
+
+    /// Cheat file to use instead of cheats/<rom name>.cht in the data
+    /// directory
+    #[arg(long, value_name = "FILE")]
+    pub cheats: Option<PathBuf>,
+
+    /// Keep this memory range (e.g. 2f0-2ff) across sessions, for games that
+    /// store high scores in RAM
+    #[arg(long, value_name = "START-END")]
+    pub persist: Option<MemoryRange>,
+
+    /// Config file to use instead of the one in the config directory
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Comma separated quirks to enable: shift-vy, load-store-i, jump-vx,
+    /// vf-reset, clip
+    #[arg(long)]
+    pub quirks: Option<Quirks>,
+
+    /// Use the quirks of a platform: vip, schip or xo-chip
+    #[arg(long, conflicts_with = "quirks")]
+    pub platform: Option<Platform>,
+
+    /// Turn a single quirk on or off, e.g. --quirk clip=false. Applied after
+    /// --quirks and --platform, may be repeated
+    #[arg(long, value_name = "NAME=BOOL", value_parser = parse_quirk)]
+    pub quirk: Vec<(String, bool)>,
+
+    /// Digits FX29 points at: octo, vip, dream6800 or eti660. Defaults to
+    /// the platform's, or octo
+    #[arg(long)]
+    pub font: Option<Font>,
+
+    /// Size of a Chip-8 pixel in window pixels, 1 to 50
+    #[arg(long, value_parser = parse_scale)]
+    pub scale: Option<u32>,
+
+    /// Instructions executed per frame, at 60 frames a second
+    #[arg(long)]
+    pub speed: Option<u32>,
+
+    /// Foreground and background colours, e.g. "#33ff66,#001100"
+    #[arg(long, value_name = "FG,BG")]
+    pub palette: Option<Palette>,
+
+    /// Turn the buzzer off
+    #[arg(long)]
+    pub mute: bool,
+
+    /// Start paused, press the pause hotkey to run
+    #[arg(long)]
+    pub start_paused: bool,
+
+    /// Stop after executing this many instructions
+    #[arg(long, value_name = "N")]
+    pub max_cycles: Option<u64>,
+
+    /// Stop after this many seconds
+    #[arg(long, value_name = "SECS")]
+    pub exit_after: Option<u64>,
+
+    /// Exit with this code when --max-cycles or --exit-after stops the run.
+    /// Errors still exit with 1
+    #[arg(long, value_name = "CODE", default_value_t = 0)]
+    pub exit_code: i32,
+
+    /// Run a second instance with these quirks next to the first one, feeding
+    /// both the same input, and report the first frame where they diverge
+    #[arg(long, value_name = "QUIRKS")]
+    pub compare: Option<Quirks>,
+
+    /// Rumble the first connected game controller while the sound timer is
+    /// running
+    #[arg(long)]
+    pub rumble: bool,
+
+    /// What to do when the program reads or writes through I past the end of
+    /// memory: wrap, clamp or error
+    #[arg(long, default_value = "wrap")]
+    pub memory_policy: MemoryPolicy,
+
+    /// What to do when the program writes below 0x200, over the interpreter
+    /// area and the font: off, warn (drop the write) or strict (stop)
+    #[arg(long, value_name = "MODE", default_value = "off")]
+    pub write_protect: WriteProtection,
+
+    /// Stop when the pc lands on an odd address instead of running from it
+    #[arg(long)]
+    pub aligned_pc: bool,
+
+    /// Maximum depth of nested subroutine calls, up to 1024. Defaults to the
+    /// platform's (12 on the VIP), or 16
+    #[arg(long, value_parser = parse_stack_depth)]
+    pub stack_depth: Option<usize>,
+
+    /// Bytes of memory, up to 65536. Defaults to the platform's (64K for
+    /// XO-CHIP), or 4096
+    #[arg(long, value_name = "BYTES", value_parser = parse_memory_size)]
+    pub memory_size: Option<usize>,
+
+    /// Seed for the random number generator, so runs can be reproduced. A
+    /// random seed is picked and printed when omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Read debugging commands from stdin while the game runs: RAM search,
+    /// watches and cheats, type `help` for the list
+    #[arg(long)]
+    pub console: bool,
+
+    /// Rhai script with on_frame, on_instruction and on_key hooks, see
+    /// src/script.rs
+    #[cfg(feature = "scripting")]
+    #[arg(long, value_name = "FILE")]
+    pub script: Option<PathBuf>,
+
+    /// Apply an IPS or BPS patch to the ROM before loading it
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "rom",
+        conflicts_with = "playlist"
+    )]
+    pub patch: Option<PathBuf>,
+
+    /// Start from a save state, or from the state in a crash dump
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["playlist", "host", "join"])]
+    pub load_state: Option<PathBuf>,
+
+    /// Record the keys pressed on every frame to this file, for playing back
+    /// with bisect-quirks
+    #[arg(long, value_name = "FILE", conflicts_with = "playlist")]
+    pub record_movie: Option<PathBuf>,
+
+    /// Write run statistics (instructions, opcode counts, frames, frame time,
+    /// audio time) to this file as JSON on exit
+    #[arg(long, value_name = "FILE")]
+    pub telemetry: Option<PathBuf>,
+
+    /// Also write the --telemetry report every this many seconds
+    #[arg(long, value_name = "SECS", requires = "telemetry")]
+    pub telemetry_interval: Option<u64>,
+
+    /// Save a BMP of which memory addresses were executed (blue), read (green)
+    /// and written (red) on exit
+    #[arg(long, value_name = "FILE", conflicts_with = "playlist")]
+    pub heatmap: Option<PathBuf>,
+
+    /// Open a window showing memory as sprites, either the given range
+    /// (e.g. 300-3ff) or the bytes around I
+    #[arg(long, value_name = "START-END", num_args = 0..=1)]
+    pub sprite_viewer: Option<Option<MemoryRange>>,
+
+    /// Show a speedrun timer that starts on the first key press and splits
+    /// when the conditions in this file are met
+    #[arg(long, value_name = "FILE", conflicts_with = "playlist")]
+    pub splits: Option<PathBuf>,
+
+    /// Show a debug strip under the screen with a graph of the delay and sound
+    /// timers and frame time statistics
+    #[arg(long)]
+    pub hud: bool,
+
+    // set by the watch command
+    #[arg(skip)]
+    pub watch: bool,
+    #[arg(skip)]
+    pub keep_state: bool,
+
+    /// Log writes to memory that was already executed as code, and the
+    /// rewritten addresses on exit
+    #[arg(long)]
+    pub self_modifying: bool,
+
+    /// Print every byte the program stores at this address (e.g. ff0) to
+    /// stdout, for printf style debugging from inside a ROM
+    #[arg(long, value_name = "ADDR", value_parser = parse_addr)]
+    pub debug_port: Option<usize>,
+
+    /// Print which opcodes were executed, and how often, on exit
+    #[arg(long)]
+    pub coverage: bool,
+
+    /// Like --coverage, also listing every address that was executed
+    #[arg(long)]
+    pub coverage_addresses: bool,
+
+    /// Host a two player netplay session on this address (e.g.
+    /// 0.0.0.0:7777) and play the left half of the keypad
+    #[arg(long, value_name = "ADDR", requires = "rom", conflicts_with_all = ["join", "compare", "serve"])]
+    pub host: Option<String>,
+
+    /// Join a netplay session on this address and play the right half of the
+    /// keypad
+    #[arg(long, value_name = "ADDR", requires = "rom", conflicts_with_all = ["compare", "serve"])]
+    pub join: Option<String>,
+
+    /// Stream the game over WebSocket on this address to viewers that can
+    /// watch and listen but not play, see assets/viewer.html
+    #[arg(long, value_name = "ADDR", conflicts_with = "serve")]
+    pub spectate: Option<String>,
+
+    /// Run headless and stream the screen over WebSocket on this address
+    /// (e.g. 127.0.0.1:8080), taking keypad input from connected clients
+    #[arg(long, value_name = "ADDR", requires = "rom")]
+    pub serve: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ConformanceArgs {
+    /// Directory holding the test ROMs and their conformance.txt manifest
+    #[arg(long, default_value = "roms/tests")]
+    pub dir: PathBuf,
+
+    /// Store the current screens as the expected results instead of checking
+    /// against them
+    #[arg(long)]
+    pub record: bool,
+
+    /// Instructions per 60Hz frame, the timers tick once every this many.
+    /// The recorded screens were taken at the default
+    #[arg(long, default_value_t = DEFAULT_SPEED)]
+    pub speed: u32,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// ROM to run
+    pub rom: PathBuf,
+
+    /// Number of instructions to execute
+    #[arg(long)]
+    pub cycles: u64,
+
+    /// Instructions per 60Hz frame, the timers tick once every this many
+    #[arg(long, default_value_t = DEFAULT_SPEED)]
+    pub speed: u32,
+
+    /// Expected screen hash, as printed by --record-expect
+    #[arg(long, value_parser = parse_hash, required_unless_present = "record_expect")]
+    pub expect: Option<u64>,
+
+    /// Print the screen hash after the run instead of checking it
+    #[arg(long, conflicts_with = "expect")]
+    pub record_expect: bool,
+
+    /// Quirks to run with, see `run --help`
+    #[arg(long, default_value = "none")]
+    pub quirks: Quirks,
+
+    /// Seed for the random number generator
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+#[derive(Args)]
+pub struct TraceArgs {
+    /// ROM to run
+    pub rom: PathBuf,
+
+    /// Reference trace, one line per instruction: pc, opcode, V0 to VF and I
+    /// in hex, see src/trace.rs
+    #[arg(long, value_name = "FILE", required_unless_present = "write")]
+    pub reference: Option<PathBuf>,
+
+    /// Write this emulator's trace in the same format instead of comparing
+    #[arg(long, value_name = "FILE", conflicts_with = "reference")]
+    pub write: Option<PathBuf>,
+
+    /// Number of instructions to trace when writing
+    #[arg(long, default_value_t = 10000)]
+    pub cycles: u64,
+
+    /// Instructions per 60Hz frame, the timers tick once every this many.
+    /// Should match the emulator the reference came from
+    #[arg(long, default_value_t = DEFAULT_SPEED)]
+    pub speed: u32,
+
+    /// Quirks to run with, see `run --help`
+    #[arg(long, default_value = "none")]
+    pub quirks: Quirks,
+
+    /// Seed for the random number generator
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+#[derive(Args)]
+pub struct BisectArgs {
+    /// ROM to run
+    pub rom: PathBuf,
+
+    /// Input movie to play back, as written by `run --record-movie`
+    #[arg(long, value_name = "FILE")]
+    pub movie: Option<PathBuf>,
+
+    /// Frames to compare the screens at, comma separated. Defaults to the end
+    /// of the movie, or frame 600 without one
+    #[arg(long, value_name = "FRAMES", value_delimiter = ',')]
+    pub at: Vec<u64>,
+
+    /// Instructions executed per frame, taken from the movie by default
+    #[arg(long)]
+    pub speed: Option<u32>,
+
+    /// Seed for the random number generator, taken from the movie by default
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Keep registers, timers, memory and the screen when reloading, only
+    /// replacing the program, instead of starting over
+    #[arg(long)]
+    pub keep_state: bool,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Args)]
+pub struct AgentArgs {
+    /// ROM to run
+    pub rom: PathBuf,
+
+    /// Address to take commands on
+    #[arg(long, default_value = "127.0.0.1:9000")]
+    pub listen: String,
+
+    /// Instructions executed per frame
+    #[arg(long, default_value_t = DEFAULT_SPEED)]
+    pub speed: u32,
+
+    /// Quirks to run with, see `run --help`
+    #[arg(long, default_value = "none")]
+    pub quirks: Quirks,
+
+    /// Seed for the random number generator
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+fn parse_quirk(s: &str) -> Result<(String, bool), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=BOOL, found `{}`", s))?;
+    let on = value
+        .parse()
+        .map_err(|_| format!("`{}` is not true or false", value))?;
+    Quirks::default().set(name, on).map_err(|e| e.to_string())?;
+    Ok((name.to_string(), on))
+}
+
+fn parse_memory_size(s: &str) -> Result<usize, String> {
+    let size = s
+        .parse()
+        .map_err(|_| format!("`{}` is not a number of bytes", s))?;
+    config::check_memory_size(size)
+}
+
+fn parse_scale(s: &str) -> Result<u32, String> {
+    let scale = s
+        .parse()
+        .map_err(|_| format!("`{}` is not a pixel size", s))?;
+    config::check_scale(scale)
+}
+
+fn parse_stack_depth(s: &str) -> Result<usize, String> {
+    let depth = s
+        .parse()
+        .map_err(|_| format!("`{}` is not a number of calls", s))?;
+    config::check_stack_depth(depth)
+}
+
+fn parse_addr(s: &str) -> Result<usize, String> {
+    usize::from_str_radix(s.trim_start_matches("0x"), 16)
+        .ok()
+        .filter(|&addr| addr <= 0xFFFF)
+        .ok_or_else(|| format!("`{}` is not a hex address", s))
+}
+
+fn parse_hash(s: &str) -> Result<u64, String> {
+    u64::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("`{}` is not a hex screen hash", s))
+}
@@ -0,0 +1,493 @@
+// Settings read from config.toml in the config directory (see paths.rs), or
+// the file given with --config. Every key is optional, anything left out keeps
+// its default, and command line flags override whatever the file says.
+use crate::cli::RunArgs;
+use crate::paths;
+use crate::persist::MemoryRange;
+use chip8_core::{
+    Font, Platform, Quirks, DEFAULT_SPEED, MAX_MEMORY_SIZE, MAX_STACK_DEPTH, MIN_MEMORY_SIZE,
+};
+use log::info;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// the qwerty keys are mapped in the following manner
+// Keypad                   QWERTY
+// +-+-+-+-+                +-+-+-+-+
+// |1|2|3|C|                |1|2|3|4|
+// +-+-+-+-+                +-+-+-+-+
+// |4|5|6|D|                |Q|W|E|R|
+// +-+-+-+-+       =>       +-+-+-+-+
+// |7|8|9|E|                |A|S|D|F|
+// +-+-+-+-+                +-+-+-+-+
+// |A|0|B|F|                |Z|X|C|V|
+// +-+-+-+-+                +-+-+-+-+
+const DEFAULT_KEYMAP: [(usize, &str); 16] = [
+    (0x1, "1"),
+    (0x2, "2"),
+    (0x3, "3"),
+    (0xc, "4"),
+    (0x4, "Q"),
+    (0x5, "W"),
+    (0x6, "E"),
+    (0xd, "R"),
+    (0x7, "A"),
+    (0x8, "S"),
+    (0x9, "D"),
+    (0xe, "F"),
+    (0xa, "Z"),
+    (0x0, "X"),
+    (0xb, "C"),
+    (0xf, "V"),
+];
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Size of a Chip-8 pixel on screen
+    #[serde(deserialize_with = "scale")]
+    pub scale: u32,
+    /// Instructions executed per 60Hz frame
+    pub speed: u32,
+    #[serde(deserialize_with = "from_str")]
+    pub quirks: Quirks,
+    /// Takes the quirks of this platform instead of `quirks`
+    #[serde(deserialize_with = "from_str_opt")]
+    pub platform: Option<Platform>,
+    /// The platform's digits when not set
+    #[serde(deserialize_with = "from_str_opt")]
+    pub font: Option<Font>,
+    /// Bytes of memory, the platform's or 4096 when not set
+    #[serde(deserialize_with = "memory_size")]
+    pub memory_size: Option<usize>,
+    /// Nested subroutine calls, the platform's or 16 when not set
+    #[serde(deserialize_with = "stack_depth")]
+    pub stack_depth: Option<usize>,
+    pub start_paused: bool,
+    pub palette: Palette,
+    /// Keypad digit ("0" to "f") to SDL key name, merged over the default
+    /// layout
+    pub keymap: BTreeMap<String, String>,
+    pub audio: Audio,
+    pub hotkeys: Hotkeys,
+    /// Overrides for single games, keyed by ROM file name
+    pub rom: BTreeMap<String, RomConfig>,
+    /// Memory kept across sessions, only set by a `[rom."name"]` section or
+    /// --persist since it depends on the game
+    #[serde(skip)]
+    pub persist: Option<MemoryRange>,
+}
+
+/// A `[rom."name"]` section, applied on top of the rest of the file when that
+/// ROM is loaded.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RomConfig {
+    pub speed: Option<u32>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub quirks: Option<Quirks>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub platform: Option<Platform>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub font: Option<Font>,
+    #[serde(deserialize_with = "memory_size")]
+    pub memory_size: Option<usize>,
+    #[serde(deserialize_with = "stack_depth")]
+    pub stack_depth: Option<usize>,
+    pub palette: Option<Palette>,
+    pub keymap: BTreeMap<String, String>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub persist: Option<MemoryRange>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            scale: 10,
+            speed: DEFAULT_SPEED,
+            quirks: Quirks::default(),
+            platform: None,
+            font: None,
+            memory_size: None,
+            stack_depth: None,
+            start_paused: false,
+            palette: Palette::default(),
+            keymap: BTreeMap::new(),
+            audio: Audio::default(),
+            hotkeys: Hotkeys::default(),
+            rom: BTreeMap::new(),
+            persist: None,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Palette {
+    #[serde(deserialize_with = "from_str")]
+    pub foreground: Rgb,
+    #[serde(deserialize_with = "from_str")]
+    pub background: Rgb,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            foreground: Rgb(255, 255, 255),
+            background: Rgb(0, 0, 0),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Audio {
+    pub enabled: bool,
+    /// Between 0 and 1
+    pub volume: f32,
+    /// Pitch of the buzzer in Hz
+    pub tone: f32,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Audio {
+            enabled: true,
+            volume: 0.25,
+            tone: 440.0,
+        }
+    }
+}
+
+/// SDL key names, see https://wiki.libsdl.org/SDL2/SDL_Keycode
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hotkeys {
+    pub quit: String,
+    pub pause: String,
+    pub reset: String,
+    pub save_state: String,
+    pub load_state: String,
+    pub screenshot: String,
+    /// Skip to the next ROM of a playlist
+    pub next: String,
+    /// Run one frame while paused
+    pub frame_advance: String,
+    /// Start or stop recording the keypad macro
+    pub record_macro: String,
+    pub play_macro: String,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Hotkeys {
+            quit: "Escape".to_string(),
+            pause: "P".to_string(),
+            reset: "F5".to_string(),
+            save_state: "F2".to_string(),
+            load_state: "F4".to_string(),
+            screenshot: "F12".to_string(),
+            next: "F6".to_string(),
+            frame_advance: "F3".to_string(),
+            record_macro: "F7".to_string(),
+            play_macro: "F8".to_string(),
+        }
+    }
+}
+
+/// Parses `#rrggbb,#rrggbb`, foreground first.
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(',') {
+            Some((fg, bg)) => Ok(Palette {
+                foreground: fg.trim().parse()?,
+                background: bg.trim().parse()?,
+            }),
+            None => Err(format!("`{}` is not a foreground,background pair", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// Parses `#rrggbb`.
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        match u32::from_str_radix(hex, 16) {
+            Ok(rgb) if hex.len() == 6 => Ok(Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)),
+            _ => Err(format!("`{}` is not a #rrggbb colour", s)),
+        }
+    }
+}
+
+impl From<Rgb> for Color {
+    fn from(rgb: Rgb) -> Color {
+        Color::RGB(rgb.0, rgb.1, rgb.2)
+    }
+}
+
+// for values that are written as strings, like quirk lists and colours
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
+
+fn from_str_opt<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    from_str(deserializer).map(Some)
+}
+
+/// Accepts sizes a machine can be built with.
+pub fn check_memory_size(size: usize) -> Result<usize, String> {
+    if (MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&size) {
+        Ok(size)
+    } else {
+        Err(format!(
+            "memory size {} is not between {} and {}",
+            size, MIN_MEMORY_SIZE, MAX_MEMORY_SIZE
+        ))
+    }
+}
+
+/// The largest pixel size, which keeps two screens side by side within what
+/// SDL can open a window for.
+pub const MAX_SCALE: u32 = 50;
+
+/// Accepts pixel sizes a window can be opened with.
+pub fn check_scale(scale: u32) -> Result<u32, String> {
+    if (1..=MAX_SCALE).contains(&scale) {
+        Ok(scale)
+    } else {
+        Err(format!(
+            "scale {} is not between 1 and {}",
+            scale, MAX_SCALE
+        ))
+    }
+}
+
+fn scale<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let scale = u32::deserialize(deserializer)?;
+    check_scale(scale).map_err(serde::de::Error::custom)
+}
+
+fn memory_size<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let size = usize::deserialize(deserializer)?;
+    check_memory_size(size)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Accepts call stack depths a machine can be built with.
+pub fn check_stack_depth(depth: usize) -> Result<usize, String> {
+    if (1..=MAX_STACK_DEPTH).contains(&depth) {
+        Ok(depth)
+    } else {
+        Err(format!(
+            "stack depth {} is not between 1 and {}",
+            depth, MAX_STACK_DEPTH
+        ))
+    }
+}
+
+fn stack_depth<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let depth = usize::deserialize(deserializer)?;
+    check_stack_depth(depth)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+// the command line, then the file, with the platform given in either place
+// filling in below each
+fn pick<T>(
+    arg: Option<T>,
+    file: Option<T>,
+    (arg_platform, file_platform): (Option<Platform>, Option<Platform>),
+    of: fn(Platform) -> T,
+) -> Option<T> {
+    arg.or(arg_platform.map(of))
+        .or(file)
+        .or(file_platform.map(of))
+}
+
+fn key(name: &str) -> Result<Keycode, String> {
+    Keycode::from_name(name).ok_or_else(|| format!("unknown key `{}`", name))
+}
+
+/// Keys resolved from the config, ready for the event loop.
+pub struct Keys {
+    pub keypad: HashMap<Keycode, usize>,
+    pub quit: Keycode,
+    pub pause: Keycode,
+    pub reset: Keycode,
+    pub save_state: Keycode,
+    pub load_state: Keycode,
+    pub screenshot: Keycode,
+    pub next: Keycode,
+    pub frame_advance: Keycode,
+    pub record_macro: Keycode,
+    pub play_macro: Keycode,
+}
+
+impl Config {
+    /// Read `path`, or the default location when none is given. A missing file
+    /// is only an error when it was asked for explicitly.
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match paths::config_file() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) if !explicit && !path.exists() => return Ok(Config::default()),
+            Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+        };
+        info!("using config {}", path.display());
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The settings for running `rom`: the file, then its `[rom."name"]`
+    /// section if there is one, then the command line.
+    pub fn for_rom(&self, rom: &Path, args: &RunArgs) -> Config {
+        let mut config = self.clone();
+        let name = rom.file_name().map(|name| name.to_string_lossy());
+        if let Some(section) = name.and_then(|name| self.rom.get(name.as_ref())) {
+            info!("applying config overrides for {}", rom.display());
+            config.speed = section.speed.unwrap_or(config.speed);
+            if let Some(quirks) = section.quirks {
+                config.quirks = quirks;
+                config.platform = None;
+            }
+            if section.platform.is_some() {
+                config.platform = section.platform;
+            }
+            if section.font.is_some() {
+                config.font = section.font;
+            }
+            if section.memory_size.is_some() {
+                config.memory_size = section.memory_size;
+            }
+            if section.stack_depth.is_some() {
+                config.stack_depth = section.stack_depth;
+            }
+            if let Some(palette) = &section.palette {
+                config.palette = palette.clone();
+            }
+            config.keymap.extend(section.keymap.clone());
+            config.persist = section.persist;
+        }
+        config.merge_args(args);
+        config
+    }
+
+    /// Apply the options given on the command line on top of the file.
+    pub fn merge_args(&mut self, args: &RunArgs) {
+        self.scale = args.scale.unwrap_or(self.scale);
+        self.speed = args.speed.unwrap_or(self.speed);
+        if let Some(palette) = &args.palette {
+            self.palette = palette.clone();
+        }
+        if args.persist.is_some() {
+            self.persist = args.persist;
+        }
+        self.audio.enabled &= !args.mute;
+        self.start_paused |= args.start_paused;
+
+        // a platform picks the starting quirks, --quirk tweaks them after
+        let mut quirks = match (args.quirks, args.platform, self.platform) {
+            (Some(quirks), _, _) => quirks,
+            (None, Some(platform), _) | (None, None, Some(platform)) => platform.quirks(),
+            (None, None, None) => self.quirks,
+        };
+        for (name, on) in &args.quirk {
+            // names were checked when the arguments were parsed
+            quirks.set(name, *on).unwrap();
+        }
+        self.quirks = quirks;
+
+        // the font, memory size and stack depth follow the platform unless
+        // they are set
+        let platforms = (args.platform, self.platform);
+        self.font = pick(args.font, self.font, platforms, Platform::font);
+        self.memory_size = pick(
+            args.memory_size,
+            self.memory_size,
+            platforms,
+            Platform::memory_size,
+        );
+        self.stack_depth = pick(
+            args.stack_depth,
+            self.stack_depth,
+            platforms,
+            Platform::stack_depth,
+        );
+    }
+
+    pub fn keys(&self) -> Result<Keys, String> {
+        let mut layout: BTreeMap<usize, String> = DEFAULT_KEYMAP
+            .iter()
+            .map(|&(digit, name)| (digit, name.to_string()))
+            .collect();
+        for (digit, name) in &self.keymap {
+            let digit = usize::from_str_radix(digit, 16)
+                .ok()
+                .filter(|&d| d < 16)
+                .ok_or_else(|| format!("`{}` is not a keypad key, expected 0 to f", digit))?;
+            layout.insert(digit, name.clone());
+        }
+        let mut keypad = HashMap::new();
+        for (digit, name) in layout {
+            if let Some(other) = keypad.insert(key(&name)?, digit) {
+                return Err(format!(
+                    "keypad keys {:x} and {:x} are both mapped to `{}`",
+                    other, digit, name
+                ));
+            }
+        }
+        Ok(Keys {
+            keypad,
+            quit: key(&self.hotkeys.quit)?,
+            pause: key(&self.hotkeys.pause)?,
+            reset: key(&self.hotkeys.reset)?,
+            save_state: key(&self.hotkeys.save_state)?,
+            load_state: key(&self.hotkeys.load_state)?,
+            screenshot: key(&self.hotkeys.screenshot)?,
+            next: key(&self.hotkeys.next)?,
+            frame_advance: key(&self.hotkeys.frame_advance)?,
+            record_macro: key(&self.hotkeys.record_macro)?,
+            play_macro: key(&self.hotkeys.play_macro)?,
+        })
+    }
+}
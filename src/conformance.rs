@@ -0,0 +1,226 @@
+// Runs the community test suite ROMs (https://github.com/Timendus/chip8-test-suite)
+// headlessly and compares the final screen against a known good one.
+//
+// The ROMs themselves are not part of this repository, drop them into the test
+// directory next to `conformance.txt`; a missing one counts as a failure. Each manifest line is
+//
+//   <rom> <quirks> <cycles> <expected screen hash> [<addr>=<value> ...]
+//
+// where the optional pokes are written to memory after loading, e.g. to pick a
+// platform in the quirks test without pressing keys. A hash of `-` means no
+// result has been recorded yet; `--record` fills in the hashes from the
+// current run once the screens have been checked by eye.
+use crate::cli::ConformanceArgs;
+use crate::headless::run_cycles;
+use chip8_core::{Chip8, Chip8Error, LoadError, Quirks, DEFAULT_MEMORY_SIZE};
+use log::{error, warn};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+const MANIFEST: &str = "conformance.txt";
+
+struct Entry {
+    rom: String,
+    quirks: Quirks,
+    cycles: u64,
+    expected: Option<u64>,
+    pokes: Vec<(usize, u8)>,
+}
+
+enum Line {
+    Entry(Entry),
+    Other(String),
+}
+
+enum Outcome {
+    Pass,
+    Fail(u64),
+    Unrecorded(u64),
+    Missing,
+    Crashed(Chip8Error),
+    Invalid(LoadError),
+}
+
+fn parse_hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+}
+
+fn parse_entry(line: &str) -> Result<Entry, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 4 {
+        return Err("expected <rom> <quirks> <cycles> <hash>".to_string());
+    }
+    let quirks = fields[1].parse().map_err(|e| format!("{}", e))?;
+    let cycles = fields[2]
+        .parse()
+        .map_err(|_| format!("bad cycle count `{}`", fields[2]))?;
+    let expected = match fields[3] {
+        "-" => None,
+        hash => Some(parse_hex(hash).ok_or_else(|| format!("bad hash `{}`", hash))?),
+    };
+    // the machines are built with the default memory size
+    let mut pokes = Vec::new();
+    for poke in &fields[4..] {
+        let parsed = poke.split_once('=').and_then(|(addr, val)| {
+            let addr = usize::try_from(parse_hex(addr)?).ok()?;
+            let val = u8::try_from(parse_hex(val)?).ok()?;
+            (addr < DEFAULT_MEMORY_SIZE).then_some((addr, val))
+        });
+        pokes.push(parsed.ok_or_else(|| format!("bad poke `{}`", poke))?);
+    }
+    Ok(Entry {
+        rom: fields[0].to_string(),
+        quirks,
+        cycles,
+        expected,
+        pokes,
+    })
+}
+
+fn parse_manifest(text: &str) -> Result<Vec<Line>, String> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                Ok(Line::Other(line.to_string()))
+            } else {
+                parse_entry(trimmed)
+                    .map(Line::Entry)
+                    .map_err(|e| format!("line {}: {}", i + 1, e))
+            }
+        })
+        .collect()
+}
+
+fn format_entry(entry: &Entry) -> String {
+    let mut line = format!(
+        "{:<20} {:<34} {:>6} {:<16}",
+        entry.rom,
+        entry.quirks.to_string(),
+        entry.cycles,
+        entry
+            .expected
+            .map_or("-".to_string(), |hash| format!("{:016x}", hash)),
+    );
+    for (addr, val) in &entry.pokes {
+        line.push_str(&format!(" {:#05x}={:#04x}", addr, val));
+    }
+    line.trim_end().to_string()
+}
+
+fn run_entry(dir: &Path, entry: &Entry, speed: u32) -> Outcome {
+    let data = match fs::read(dir.join(&entry.rom)) {
+        Ok(data) => data,
+        Err(_) => return Outcome::Missing,
+    };
+    // a fixed seed keeps the screens comparable between runs
+    let mut c8 = Chip8::builder().quirks(entry.quirks).seed(0).build();
+    if let Err(e) = c8.load_rom(&data) {
+        return Outcome::Invalid(e);
+    }
+    for &(addr, val) in &entry.pokes {
+        c8.memory_mut()[addr] = val;
+    }
+    if let Err(e) = run_cycles(&mut c8, entry.cycles, speed) {
+        return Outcome::Crashed(e);
+    }
+
+    let hash = c8.framebuffer_hash();
+    match entry.expected {
+        Some(expected) if expected == hash => Outcome::Pass,
+        Some(_) => Outcome::Fail(hash),
+        None => Outcome::Unrecorded(hash),
+    }
+}
+
+pub fn run(args: ConformanceArgs) {
+    let manifest_path = args.dir.join(MANIFEST);
+    let text = fs::read_to_string(&manifest_path).unwrap_or_else(|e| {
+        error!("cannot read {}: {}", manifest_path.display(), e);
+        exit(1);
+    });
+    let mut lines = parse_manifest(&text).unwrap_or_else(|e| {
+        error!("{}: {}", manifest_path.display(), e);
+        exit(1);
+    });
+
+    let mut outcomes = Vec::new();
+    for line in lines.iter_mut() {
+        if let Line::Entry(entry) = line {
+            let outcome = run_entry(&args.dir, entry, args.speed);
+            if args.record {
+                if let Outcome::Fail(hash) | Outcome::Unrecorded(hash) = outcome {
+                    entry.expected = Some(hash);
+                }
+            }
+            // the pokes tell apart the tests of a ROM that holds several
+            let mut name = entry.rom.clone();
+            for (addr, val) in &entry.pokes {
+                name.push_str(&format!(" {:#05x}={:#04x}", addr, val));
+            }
+            outcomes.push((name, entry.quirks, outcome));
+        }
+    }
+
+    // one table per quirk configuration, in manifest order
+    let mut configs: Vec<Quirks> = Vec::new();
+    for (_, quirks, _) in &outcomes {
+        if !configs.contains(quirks) {
+            configs.push(*quirks);
+        }
+    }
+    let mut failed = false;
+    let checked = outcomes
+        .iter()
+        .filter(|(_, _, outcome)| matches!(outcome, Outcome::Pass | Outcome::Fail(_)))
+        .count();
+    for quirks in configs {
+        println!("quirks: {}", quirks);
+        for (rom, _, outcome) in outcomes.iter().filter(|(_, q, _)| *q == quirks) {
+            let result = match outcome {
+                Outcome::Pass => "pass".to_string(),
+                Outcome::Fail(hash) => {
+                    failed = true;
+                    format!("FAIL (screen {:016x})", hash)
+                }
+                Outcome::Unrecorded(hash) => format!("no reference (screen {:016x})", hash),
+                Outcome::Missing => {
+                    failed = true;
+                    "FAIL (missing rom)".to_string()
+                }
+                Outcome::Invalid(e) => {
+                    failed = true;
+                    format!("FAIL ({})", e)
+                }
+                Outcome::Crashed(e) => {
+                    failed = true;
+                    format!("FAIL ({})", e)
+                }
+            };
+            println!("  {:<44} {}", rom, result);
+        }
+    }
+    if checked == 0 && !args.record {
+        warn!("no screen was checked against a recorded reference");
+    }
+
+    if args.record {
+        let text: Vec<String> = lines
+            .iter()
+            .map(|line| match line {
+                Line::Entry(entry) => format_entry(entry),
+                Line::Other(other) => other.clone(),
+            })
+            .collect();
+        if let Err(e) = fs::write(&manifest_path, text.join("\n") + "\n") {
+            error!("cannot write {}: {}", manifest_path.display(), e);
+            exit(1);
+        }
+        println!("recorded results to {}", manifest_path.display());
+    } else if failed {
+        exit(1);
+    }
+}
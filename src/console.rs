@@ -0,0 +1,176 @@
+// Commands typed on stdin while a game runs (--console), for poking around in
+// memory: RAM search, watches and adding cheats on the fly. Lines are read on
+// a separate thread and handled between frames.
+use crate::cheats::Cheats;
+use crate::octo::Symbols;
+use crate::ramsearch::RamSearch;
+use chip8_core::Chip8;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+// candidates listed before `list` gives up and just prints the count
+const LIST_LIMIT: usize = 32;
+
+const HELP: &str = "\
+search                        start a RAM search over all of memory
+changed | unchanged           keep the addresses that changed, or did not,
+increased | decreased         ... went up or went down since the last filter
+eq <value>                    ... now hold <value>
+list                          show the remaining addresses and their values
+watch <addr> / unwatch <addr> report whenever <addr> changes
+labels                        list the labels of an Octo source
+where                         show the pc, and the label it is in
+cheat <addr> <value> <freeze|once>
+                              poke <addr> and save it to the cheat file";
+
+pub struct Console {
+    lines: Receiver<String>,
+    search: Option<RamSearch>,
+    // address to the last value reported
+    watches: BTreeMap<usize, u8>,
+    cheat_path: Option<PathBuf>,
+    symbols: Symbols,
+}
+
+// a hex address, or a label of the source
+fn parse_addr(s: Option<&str>, c8: &Chip8, symbols: &Symbols) -> Result<usize, String> {
+    let s = s.ok_or("missing address")?;
+    usize::from_str_radix(s.trim_start_matches("0x"), 16)
+        .ok()
+        .or_else(|| symbols.get(s))
+        .filter(|&addr| addr < c8.memory().len())
+        .ok_or_else(|| format!("bad address `{}`", s))
+}
+
+impl Console {
+    /// Start reading commands from stdin.
+    pub fn new() -> Console {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) => {
+                        if tx.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+        println!("console ready, type `help` for the commands");
+        Console {
+            lines: rx,
+            search: None,
+            watches: BTreeMap::new(),
+            cheat_path: None,
+            symbols: Symbols::default(),
+        }
+    }
+
+    /// Append cheats added from now on to `path`, the cheat file of the ROM
+    /// being played.
+    pub fn set_cheat_path(&mut self, path: Option<PathBuf>) {
+        self.cheat_path = path;
+    }
+
+    /// Use the labels of the ROM being played for addresses.
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    /// Run the commands typed since the last frame, then report watches
+    /// that changed.
+    pub fn update(&mut self, c8: &mut Chip8, cheats: &mut Cheats) {
+        while let Ok(line) = self.lines.try_recv() {
+            if let Err(e) = self.run(line.trim(), c8, cheats) {
+                println!("{}", e);
+            }
+        }
+        for (&addr, last) in self.watches.iter_mut() {
+            let value = c8.memory()[addr];
+            if value != *last {
+                println!("watch {:#05x}: {:#04x} -> {:#04x}", addr, last, value);
+                *last = value;
+            }
+        }
+    }
+
+    fn run(&mut self, line: &str, c8: &mut Chip8, cheats: &mut Cheats) -> Result<(), String> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => {}
+            Some("help") => println!("{}", HELP),
+            Some("search") => {
+                self.search = Some(RamSearch::new(c8.memory()));
+                println!("{} candidates", c8.memory().len());
+            }
+            Some("changed" | "unchanged" | "increased" | "decreased" | "eq") => {
+                let search = self
+                    .search
+                    .as_mut()
+                    .ok_or("no search running, type `search` first")?;
+                search.filter(c8.memory(), line.parse()?);
+                println!("{} candidates", search.candidates().len());
+            }
+            Some("list") => {
+                let search = self
+                    .search
+                    .as_ref()
+                    .ok_or("no search running, type `search` first")?;
+                let candidates = search.candidates();
+                if candidates.len() > LIST_LIMIT {
+                    println!("{} candidates, filter some more", candidates.len());
+                }
+                for &addr in candidates.iter().take(LIST_LIMIT) {
+                    println!("  {:#05x} = {:#04x}", addr, c8.memory()[addr]);
+                }
+            }
+            Some("watch") => {
+                let addr = parse_addr(words.next(), c8, &self.symbols)?;
+                let value = c8.memory()[addr];
+                self.watches.insert(addr, value);
+                println!("watching {:#05x} = {:#04x}", addr, value);
+            }
+            Some("unwatch") => {
+                let addr = parse_addr(words.next(), c8, &self.symbols)?;
+                self.watches.remove(&addr);
+            }
+            Some("labels") => {
+                if self.symbols.is_empty() {
+                    println!("no labels, they come from assembling a .o8 source");
+                }
+                for (addr, name) in self.symbols.iter() {
+                    println!("  {:#05x} {}", addr, name);
+                }
+            }
+            Some("where") => match self.symbols.describe(c8.pc()) {
+                Some(label) => println!("pc {:#05x} ({})", c8.pc(), label),
+                None => println!("pc {:#05x}", c8.pc()),
+            },
+            Some("cheat") => {
+                let cheat = words.collect::<Vec<_>>().join(" ");
+                cheats.add(&cheat, c8.memory().len())?;
+                cheats.apply_last(c8);
+                match &self.cheat_path {
+                    Some(path) => {
+                        OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(path)
+                            .and_then(|mut file| writeln!(file, "{}", cheat))
+                            .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+                        println!("added to {}", path.display());
+                    }
+                    None => println!("added for this session"),
+                }
+            }
+            Some(other) => return Err(format!("unknown command `{}`, type `help`", other)),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+// Records which instructions a session executed, for `--coverage`.
+use chip8_core::{decode, Chip8, Opcode, PATTERNS};
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+pub struct Coverage {
+    // executions per opcode pattern
+    patterns: BTreeMap<&'static str, u64>,
+    // executions per address, with the opcode last seen there
+    addresses: BTreeMap<usize, (Opcode, u64)>,
+}
+
+impl Coverage {
+    /// Record the instruction the last `tick_cpu` executed.
+    pub fn record(&mut self, c8: &Chip8) {
+        if let Some((pc, oc)) = c8.recent_instructions().last() {
+            *self.patterns.entry(decode(oc).pattern()).or_insert(0) += 1;
+            let entry = self.addresses.entry(pc).or_insert((oc, 0));
+            *entry = (oc, entry.1 + 1);
+        }
+    }
+
+    pub fn print(&self, per_address: bool) {
+        let covered = PATTERNS
+            .iter()
+            .filter(|p| self.patterns.contains_key(*p))
+            .count();
+        println!("opcode coverage: {}/{}", covered, PATTERNS.len());
+        for pattern in PATTERNS.iter() {
+            match self.patterns.get(pattern) {
+                Some(count) => println!("  {}  {:>10}", pattern, count),
+                None => println!("  {}  {:>10}", pattern, "never"),
+            }
+        }
+        if let Some(count) = self.patterns.get("????") {
+            println!("  ????  {:>10}  (ignored opcodes)", count);
+        }
+
+        if per_address {
+            println!("executed addresses: {}", self.addresses.len());
+            for (pc, (oc, count)) in &self.addresses {
+                println!(
+                    "  {:#05x}  {:04x}  {:<4}  {:>10}",
+                    pc,
+                    oc,
+                    decode(*oc).pattern(),
+                    count
+                );
+            }
+        }
+    }
+}
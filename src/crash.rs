@@ -0,0 +1,105 @@
+// Crash dumps, written to crashes/ in the data directory whenever a ROM stops
+// with an error, so a bug report carries everything needed to reproduce it.
+// The file is JSON: the error, the ROM's SHA-1, the machine settings, the
+// last instructions executed and the save state (see chip8-core's state.rs)
+// as a hex string. `--load-state` takes a dump as well as a plain state file
+// to start from the moment of the crash.
+use crate::paths;
+use chip8_core::{decode, Chip8, Chip8Error};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize)]
+struct Instruction {
+    pc: String,
+    opcode: String,
+    decoded: String,
+}
+
+#[derive(Serialize)]
+struct Dump {
+    version: &'static str,
+    error: String,
+    /// SHA-1 of the ROM, empty when none was loaded
+    rom: String,
+    quirks: String,
+    /// Instructions per frame
+    speed: u32,
+    aligned_pc: bool,
+    font: String,
+    seed: u64,
+    memory_size: usize,
+    memory_policy: String,
+    write_protection: String,
+    stack_depth: usize,
+    /// Oldest first, the last one is the instruction that failed
+    recent_instructions: Vec<Instruction>,
+    state: String,
+}
+
+// the part of a dump --load-state needs
+#[derive(Deserialize)]
+struct StateOnly {
+    state: String,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// an odd digit out fails the last pair
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Write a dump of `c8` after it stopped with `error` while running at
+/// `speed`, returning where it went.
+pub fn write(c8: &Chip8, speed: u32, error: Chip8Error) -> Result<PathBuf, String> {
+    let rom = c8.rom_id().map(|rom| rom.sha1_hex()).unwrap_or_default();
+    let taken = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_secs());
+    let path = paths::crash_file(&rom, taken).ok_or("no data directory for crash dumps")?;
+    let dump = Dump {
+        version: env!("CARGO_PKG_VERSION"),
+        error: error.to_string(),
+        rom,
+        quirks: c8.quirks().to_string(),
+        speed,
+        aligned_pc: c8.aligned_pc(),
+        font: c8.font().to_string(),
+        seed: c8.seed(),
+        memory_size: c8.memory().len(),
+        memory_policy: c8.memory_policy().to_string(),
+        write_protection: c8.write_protection().to_string(),
+        stack_depth: c8.stack_depth(),
+        recent_instructions: c8
+            .recent_instructions()
+            .map(|(pc, oc)| Instruction {
+                pc: format!("{:03x}", pc),
+                opcode: format!("{:04x}", oc),
+                decoded: format!("{:?}", decode(oc)),
+            })
+            .collect(),
+        state: hex(&c8.save_state()),
+    };
+    let json = serde_json::to_string_pretty(&dump).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// The save state in `path`, either a state file or a crash dump.
+pub fn read_state(path: &Path) -> Result<Vec<u8>, String> {
+    let data = fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    if !data.starts_with(b"{") {
+        return Ok(data);
+    }
+    let dump: StateOnly = serde_json::from_slice(&data)
+        .map_err(|e| format!("{} is not a crash dump: {}", path.display(), e))?;
+    from_hex(&dump.state).ok_or_else(|| format!("{} has a broken state", path.display()))
+}
@@ -0,0 +1,68 @@
+// Running without a window, for the test and verification commands.
+use chip8_core::{Chip8, Chip8Error};
+use std::time::{Duration, Instant};
+
+/// Runs instructions one at a time and ticks the timers after every `speed`
+/// of them, a frame's worth.
+pub struct Clock {
+    speed: u32,
+    since_tick: u32,
+}
+
+impl Clock {
+    pub fn new(speed: u32) -> Clock {
+        Clock {
+            speed: speed.max(1),
+            since_tick: 0,
+        }
+    }
+
+    pub fn step(&mut self, c8: &mut Chip8) -> Result<(), Chip8Error> {
+        c8.tick_cpu()?;
+        self.since_tick += 1;
+        if self.since_tick == self.speed {
+            self.since_tick = 0;
+            c8.tick_60hz();
+        }
+        Ok(())
+    }
+}
+
+/// Run `cycles` instructions at `speed` per frame.
+pub fn run_cycles(c8: &mut Chip8, cycles: u64, speed: u32) -> Result<(), Chip8Error> {
+    let mut clock = Clock::new(speed);
+    for _ in 0..cycles {
+        clock.step(c8)?;
+    }
+    Ok(())
+}
+
+/// Where --max-cycles and --exit-after stop a run, counting from when this is
+/// made and across playlist entries.
+pub struct Limits {
+    max_cycles: Option<u64>,
+    deadline: Option<Instant>,
+    cycles: u64,
+}
+
+impl Limits {
+    pub fn new(max_cycles: Option<u64>, exit_after: Option<u64>) -> Limits {
+        Limits {
+            max_cycles,
+            deadline: exit_after.map(|secs| Instant::now() + Duration::from_secs(secs)),
+            cycles: 0,
+        }
+    }
+
+    /// Count an executed instruction.
+    pub fn count(&mut self) {
+        self.cycles += 1;
+    }
+
+    pub fn reached(&self) -> bool {
+        self.max_cycles.is_some_and(|max| self.cycles >= max)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
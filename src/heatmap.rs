@@ -0,0 +1,59 @@
+// --heatmap: an image of how memory was used, one cell per address, 64
+// addresses to a row starting with 0x000 at the top left. Executed bytes are
+// blue, bytes read through I are green and bytes written are red, mixed where
+// an address was used more than one way, brighter the more often it was used.
+// Untouched memory stays black.
+use chip8_core::AccessCounts;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use std::path::Path;
+
+const COLUMNS: usize = 64;
+const CELL: usize = 8;
+
+// brightness of `count` on a log scale up to `max`, so addresses used a
+// handful of times still show up next to a hot loop
+fn level(count: u32, max: u32) -> u8 {
+    if count == 0 {
+        return 0;
+    }
+    let scaled = (count as f64).ln_1p() / (max as f64).ln_1p();
+    // the dimmest used address is still visible against the background
+    (64.0 + scaled * 191.0) as u8
+}
+
+pub fn save(accesses: &AccessCounts, path: &Path) -> Result<(), String> {
+    let max = |counts: &[u32]| counts.iter().copied().max().unwrap_or(0);
+    let (max_reads, max_writes, max_executes) = (
+        max(&accesses.reads),
+        max(&accesses.writes),
+        max(&accesses.executes),
+    );
+
+    let rows = accesses.reads.len().div_ceil(COLUMNS);
+    let (width, height) = (COLUMNS * CELL, rows * CELL);
+    let mut data = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let addr = (y / CELL) * COLUMNS + x / CELL;
+            // a thin line between cells keeps neighbouring addresses apart
+            if addr >= accesses.reads.len() || x % CELL == 0 || y % CELL == 0 {
+                data.extend_from_slice(&[0x20, 0x20, 0x20]);
+                continue;
+            }
+            data.extend_from_slice(&[
+                level(accesses.writes[addr], max_writes),
+                level(accesses.reads[addr], max_reads),
+                level(accesses.executes[addr], max_executes),
+            ]);
+        }
+    }
+    let surface = Surface::from_data(
+        &mut data,
+        width as u32,
+        height as u32,
+        (width * 3) as u32,
+        PixelFormatEnum::RGB24,
+    )?;
+    surface.save_bmp(path)
+}
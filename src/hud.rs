@@ -0,0 +1,213 @@
+// --hud: a debug strip under the game screen. The left half plots the delay
+// and sound timers over the last few seconds, delay in green and sound in
+// yellow, so timing loops can be checked at different speeds. The graph scales
+// to the largest value on it.
+//
+// The right half shows where the frames of the last second went: emulating
+// (the CPU loop and everything else between frames), rendering (drawing and
+// SDL's present) and sleeping until the next frame, as averages in
+// milliseconds. The histogram next to them counts how far the frames were off
+// 1/60 of a second, so a slow CPU loop, a slow present and an OS that wakes the
+// thread late can be told apart.
+use crate::config::Rgb;
+use crate::text::{draw_text, GLYPH_HEIGHT, GLYPH_WIDTH};
+use chip8_core::Chip8;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub const HEIGHT: u32 = 96;
+// three seconds of 60Hz frames
+const HISTORY: usize = 180;
+const TEXT_SCALE: u32 = 2;
+const LABEL_HEIGHT: u32 = (GLYPH_HEIGHT + 2) * TEXT_SCALE;
+// keeps short timer values from filling the whole graph
+const MIN_SCALE: u8 = 15;
+
+// one second of frames for the performance panel
+const PERF_HISTORY: usize = 60;
+// upper bounds of the jitter histogram buckets in ms, the last one is open
+const JITTER_BUCKETS: [(f64, &str); 6] = [
+    (0.5, "<.5"),
+    (1.0, "<1"),
+    (2.0, "<2"),
+    (4.0, "<4"),
+    (8.0, "<8"),
+    (f64::INFINITY, "8+"),
+];
+
+const DELAY: Rgb = Rgb(64, 224, 64);
+const SOUND: Rgb = Rgb(240, 208, 32);
+const LABEL: Rgb = Rgb(160, 160, 160);
+const EMULATE: Rgb = Rgb(64, 192, 240);
+const RENDER: Rgb = Rgb(224, 96, 224);
+const SLEEP: Rgb = Rgb(128, 128, 128);
+const JITTER: Rgb = Rgb(240, 128, 64);
+
+/// Where the time of one frame went.
+#[derive(Clone, Copy)]
+pub struct FrameTimes {
+    pub emulate: Duration,
+    pub render: Duration,
+    pub sleep: Duration,
+    /// From the start of the frame to the start of the next one
+    pub total: Duration,
+}
+
+#[derive(Default)]
+pub struct Hud {
+    // (delay, sound) at the end of each frame, oldest first
+    timers: VecDeque<(u8, u8)>,
+    times: VecDeque<FrameTimes>,
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Hud {
+    /// Remember the timers after an emulated frame.
+    pub fn record(&mut self, c8: &Chip8) {
+        if self.timers.len() == HISTORY {
+            self.timers.pop_front();
+        }
+        self.timers.push_back((c8.delay_timer(), c8.sound_timer()));
+    }
+
+    /// Remember the timing of a frame, paused or not.
+    pub fn record_times(&mut self, times: FrameTimes) {
+        if self.times.len() == PERF_HISTORY {
+            self.times.pop_front();
+        }
+        self.times.push_back(times);
+    }
+
+    pub fn clear(&mut self) {
+        self.timers.clear();
+        self.times.clear();
+    }
+
+    /// Draw the HUD into the strip of `width` pixels starting at `top`.
+    pub fn draw(&self, canvas: &mut Canvas<Window>, top: i32, width: u32) {
+        canvas.set_draw_color(Color::RGB(0x20, 0x20, 0x20));
+        canvas.fill_rect(Rect::new(0, top, width, HEIGHT)).unwrap();
+        self.draw_timers(canvas, top, width / 2);
+        self.draw_perf(canvas, (width / 2) as i32, top, width - width / 2);
+    }
+
+    fn draw_timers(&self, canvas: &mut Canvas<Window>, top: i32, width: u32) {
+        let (delay, sound) = self.timers.back().copied().unwrap_or_default();
+        let max = self
+            .timers
+            .iter()
+            .map(|&(delay, sound)| delay.max(sound))
+            .max()
+            .unwrap_or(0)
+            .max(MIN_SCALE);
+        canvas.set_draw_color(Color::from(DELAY));
+        let label = format!("DT {:3}", delay);
+        draw_text(canvas, 4, top + 2, TEXT_SCALE, &label);
+        canvas.set_draw_color(Color::from(SOUND));
+        let label = format!("ST {:3}", sound);
+        draw_text(canvas, 64, top + 2, TEXT_SCALE, &label);
+        canvas.set_draw_color(Color::from(LABEL));
+        let label = format!("MAX {}", max);
+        draw_text(canvas, 124, top + 2, TEXT_SCALE, &label);
+
+        let graph_top = top + LABEL_HEIGHT as i32;
+        let graph_height = HEIGHT - LABEL_HEIGHT - 2;
+        let step = width as f32 / HISTORY as f32;
+        // the newest sample sits at the right edge
+        let first = HISTORY - self.timers.len();
+        let y = |value: u8| {
+            graph_top + (graph_height - value as u32 * graph_height / max as u32) as i32
+        };
+        for (i, &(delay, sound)) in self.timers.iter().enumerate() {
+            let x = ((first + i) as f32 * step) as i32;
+            let w = step.ceil() as u32;
+            canvas.set_draw_color(Color::from(DELAY));
+            canvas.fill_rect(Rect::new(x, y(delay) - 1, w, 2)).unwrap();
+            canvas.set_draw_color(Color::from(SOUND));
+            canvas.fill_rect(Rect::new(x, y(sound) - 1, w, 2)).unwrap();
+        }
+    }
+
+    fn draw_perf(&self, canvas: &mut Canvas<Window>, left: i32, top: i32, width: u32) {
+        canvas.set_draw_color(Color::RGB(128, 128, 128));
+        canvas.fill_rect(Rect::new(left, top, 2, HEIGHT)).unwrap();
+        let left = left + 6;
+
+        let count = self.times.len().max(1) as f64;
+        let average = |part: fn(&FrameTimes) -> Duration| {
+            self.times.iter().map(|t| ms(part(t))).sum::<f64>() / count
+        };
+        let jitter = |t: &FrameTimes| (ms(t.total) - ms(crate::FRAME)).abs();
+        let lines = [
+            (LABEL, "FRAME MS".to_string()),
+            (EMULATE, format!("EMU {:5.2}", average(|t| t.emulate))),
+            (RENDER, format!("REN {:5.2}", average(|t| t.render))),
+            (SLEEP, format!("SLP {:5.2}", average(|t| t.sleep))),
+            (
+                JITTER,
+                format!(
+                    "JIT {:5.2}",
+                    self.times.iter().map(jitter).sum::<f64>() / count
+                ),
+            ),
+        ];
+        for (i, (color, text)) in lines.iter().enumerate() {
+            canvas.set_draw_color(Color::from(*color));
+            draw_text(
+                canvas,
+                left,
+                top + 2 + (i as u32 * LABEL_HEIGHT) as i32,
+                TEXT_SCALE,
+                text,
+            );
+        }
+
+        // the histogram goes right of the 9 character column
+        let graph_left = left + (10 * (GLYPH_WIDTH + 1) * TEXT_SCALE) as i32;
+        let graph_width = (left + width as i32 - 6 - graph_left).max(0) as u32;
+        let mut buckets = [0u32; JITTER_BUCKETS.len()];
+        for t in &self.times {
+            let jitter = jitter(t);
+            let bucket = JITTER_BUCKETS
+                .iter()
+                .position(|&(to, _)| jitter < to)
+                .unwrap();
+            buckets[bucket] += 1;
+        }
+        canvas.set_draw_color(Color::from(LABEL));
+        draw_text(canvas, graph_left, top + 2, TEXT_SCALE, "JITTER");
+        let bar_top = top + LABEL_HEIGHT as i32;
+        let bar_height = HEIGHT - 2 * LABEL_HEIGHT - 2;
+        let bar_width = graph_width / JITTER_BUCKETS.len() as u32;
+        for (i, (&n, &(_, label))) in buckets.iter().zip(JITTER_BUCKETS.iter()).enumerate() {
+            let x = graph_left + (i as u32 * bar_width) as i32;
+            let h = n * bar_height / PERF_HISTORY as u32;
+            canvas.set_draw_color(Color::from(JITTER));
+            if h > 0 {
+                canvas
+                    .fill_rect(Rect::new(
+                        x,
+                        bar_top + (bar_height - h) as i32,
+                        bar_width.saturating_sub(2).max(1),
+                        h,
+                    ))
+                    .unwrap();
+            }
+            canvas.set_draw_color(Color::from(LABEL));
+            draw_text(
+                canvas,
+                x,
+                bar_top + bar_height as i32 + 2,
+                TEXT_SCALE,
+                label,
+            );
+        }
+    }
+}
@@ -0,0 +1,127 @@
+// Keypad macros: a stretch of input recorded with the record_macro hotkey (F7
+// to start, again to stop) and played back with play_macro (F8), e.g. to skip
+// a game's menus or set up a bug again. One macro per ROM, kept in
+// macros/<rom name>.macro in the data directory so it survives restarts:
+//
+//   chip8-rs macro 1
+//   <frame> <keys>
+//   ...
+//
+// with frames counted from the start of the macro, read and written as a
+// movie.rs `InputTrack`. The last line is the frame the macro ends on.
+use crate::movie::InputTrack;
+use chip8_core::Chip8;
+use log::{info, warn};
+use std::fs;
+use std::path::PathBuf;
+
+const HEADER: &str = "chip8-rs macro 1";
+
+enum State {
+    Idle,
+    Recording { start: u64 },
+    Playing { start: u64 },
+}
+
+pub struct Macros {
+    track: InputTrack,
+    state: State,
+    path: Option<PathBuf>,
+}
+
+fn parse(text: &str) -> Result<InputTrack, String> {
+    let mut lines = text.lines();
+    if lines.next() != Some(HEADER) {
+        return Err("not a chip8-rs macro".to_string());
+    }
+    InputTrack::parse(lines)
+}
+
+fn set_keys(machines: &mut [Chip8], keys: u16) {
+    for c8 in machines {
+        c8.set_keys(keys);
+    }
+}
+
+impl Macros {
+    /// The macro saved at `path`, or none yet.
+    pub fn load(path: Option<PathBuf>) -> Macros {
+        let track = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(text)) => parse(&text).unwrap_or_else(|e| {
+                warn!("{}: {}", path.as_ref().unwrap().display(), e);
+                InputTrack::default()
+            }),
+            _ => InputTrack::default(),
+        };
+        Macros {
+            track,
+            state: State::Idle,
+            path,
+        }
+    }
+
+    /// Start recording, or stop and save the recording.
+    pub fn toggle_recording(&mut self, frame: u64) {
+        match self.state {
+            State::Recording { start } => {
+                self.track.set_end(frame.saturating_sub(start));
+                self.state = State::Idle;
+                info!("recorded a {} frame macro", self.track.end());
+                self.save();
+            }
+            _ => {
+                self.track = InputTrack::default();
+                self.state = State::Recording { start: frame };
+                info!("recording macro");
+            }
+        }
+    }
+
+    /// Play the macro from `frame` on.
+    pub fn play(&mut self, frame: u64) {
+        match self.state {
+            State::Recording { .. } => warn!("stop recording the macro first"),
+            _ if self.track.is_empty() => warn!("no macro recorded for this ROM"),
+            _ => self.state = State::Playing { start: frame },
+        }
+    }
+
+    /// Call before running `frame`: notes the keys while recording, and
+    /// holds the macro's keys while playing.
+    pub fn update(&mut self, frame: u64, machines: &mut [Chip8]) {
+        match self.state {
+            State::Idle => {}
+            // the machines were reset since, which ends a recording too
+            State::Recording { start } | State::Playing { start } if frame < start => {
+                if let State::Recording { .. } = self.state {
+                    info!("recorded a {} frame macro", self.track.end());
+                    self.save();
+                }
+                self.state = State::Idle;
+            }
+            State::Recording { start } => {
+                self.track.hold(frame - start, machines[0].keys());
+                self.track.set_end(frame - start + 1);
+            }
+            State::Playing { start } => {
+                let at = frame - start;
+                if at >= self.track.end() {
+                    // let go of everything the macro was holding
+                    set_keys(machines, 0);
+                    self.state = State::Idle;
+                    return;
+                }
+                set_keys(machines, self.track.keys_at(at));
+            }
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else { return };
+        let mut text = format!("{}\n", HEADER);
+        self.track.write(&mut text);
+        if let Err(e) = fs::write(path, text) {
+            warn!("cannot save the macro to {}: {}", path.display(), e);
+        }
+    }
+}
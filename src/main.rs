@@ -1,18 +1,84 @@
 extern crate sdl2;
 
-use rand::prelude::*;
-use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
-use sdl2::event::{Event, EventType};
-use sdl2::keyboard::Keycode;
+mod agent;
+mod archive;
+mod bisect;
+mod cheats;
+mod cli;
+mod config;
+mod conformance;
+mod console;
+mod coverage;
+mod crash;
+mod headless;
+mod heatmap;
+mod hud;
+mod macros;
+mod movie;
+mod netplay;
+mod octo;
+mod patch;
+mod paths;
+mod persist;
+mod picker;
+mod ramsearch;
+mod recent;
+mod rom;
+mod rumble;
+mod screenshot;
+#[cfg(feature = "scripting")]
+mod script;
+mod selfmod;
+mod serve;
+mod speedrun;
+mod sprites;
+mod telemetry;
+mod text;
+mod trace;
+mod verify;
+mod watch;
+
+use cheats::Cheats;
+use chip8_core::{
+    decode, random_seed, Chip8, Chip8Error, Quirks, RomId, DEFAULT_MEMORY_SIZE,
+    DEFAULT_STACK_DEPTH, HEIGHT, WIDTH,
+};
+use clap::Parser;
+use cli::{Cli, Command, RunArgs, WatchArgs};
+use config::Config;
+use console::Console;
+use coverage::Coverage;
+use env_logger::Env;
+use headless::Limits;
+use hud::{FrameTimes, Hud};
+use log::{debug, error, info, warn, LevelFilter};
+use macros::Macros;
+use movie::Movie;
+use netplay::Netplay;
+use octo::Symbols;
+use persist::Persist;
+use rumble::Rumble;
+use sdl2::audio::{AudioCallback, AudioSpecDesired};
+use sdl2::event::{Event, WindowEvent};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
-use std::env;
-use std::fs::File;
-use std::io::Read;
+use selfmod::SelfModifying;
+use speedrun::Speedrun;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::{thread, time};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use telemetry::Telemetry;
+
+// set at build time through CHIP8_EMBED_ROM, see build.rs. Empty otherwise
+const EMBEDDED_ROM: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/embedded.rom"));
+const EMBEDDED_ROM_NAME: &str = env!("CHIP8_EMBEDDED_ROM_NAME");
+
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
 
 struct SquareWave {
     phase_inc: f32,
@@ -36,561 +102,961 @@ impl AudioCallback for SquareWave {
     }
 }
 
-type Opcode = u16;
-
-enum Instruction {
-    JumpTo(usize),
-    Subroutine(usize),
+// side by side instances are drawn next to each other, with pixels that differ
+// from the left hand instance highlighted
+fn rerender(
+    canvas: &mut Canvas<Window>,
+    machines: &[Chip8],
+    config: &Config,
+    hud: Option<&Hud>,
+    speedrun: Option<&Speedrun>,
+) {
+    let palette = &config.palette;
+    canvas.set_draw_color(Color::from(palette.background));
+    canvas.clear();
+    let scale = config.scale as usize;
+    let reference = machines[0].pixel_buffer();
+    for (i, c8) in machines.iter().enumerate() {
+        let x_offset = (i * WIDTH * scale) as i32;
+        for (y, row) in c8.pixel_buffer().iter().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                let color = if pixel != reference[y][x] {
+                    Color::RGB(255, 0, 0)
+                } else if pixel {
+                    Color::from(palette.foreground)
+                } else {
+                    continue;
+                };
+                canvas.set_draw_color(color);
+                canvas
+                    .fill_rect(Rect::new(
+                        x_offset + (x * scale) as i32,
+                        (y * scale) as i32,
+                        scale as u32,
+                        scale as u32,
+                    ))
+                    .unwrap();
+            }
+        }
+        if i > 0 {
+            canvas.set_draw_color(Color::RGB(128, 128, 128));
+            canvas
+                .fill_rect(Rect::new(x_offset - 1, 0, 2, (HEIGHT * scale) as u32))
+                .unwrap();
+        }
+    }
+    if let Some(speedrun) = speedrun {
+        speedrun.draw(canvas, (WIDTH * scale) as u32);
+    }
+    if let Some(hud) = hud {
+        let width = (WIDTH * scale * machines.len()) as u32;
+        hud.draw(canvas, (HEIGHT * scale) as i32, width);
+    }
+    canvas.present();
+}
 
-    SkipIfRegisterEqualValue(usize, u8),
-    SkipIfRegisterNotEqualValue(usize, u8),
+/// Move `next_frame` on by a frame and sleep until then, so loops run at
+/// 60Hz, catching up without sleeping if they fell behind. Returns when the
+/// wait started.
+pub fn wait_for_frame(next_frame: &mut Instant) -> Instant {
+    *next_frame += FRAME;
+    let now = Instant::now();
+    if *next_frame > now {
+        thread::sleep(*next_frame - now);
+    } else {
+        *next_frame = now;
+    }
+    now
+}
 
-    SkipIfRegisterEqualRegister(usize, usize),
+/// Report `c8` stopping with `error` while running at `speed`, and write a
+/// crash dump.
+pub fn report_crash(c8: &Chip8, speed: u32, error: Chip8Error) {
+    error!("{}", error);
+    if let Some(rom) = c8.rom_id() {
+        error!("rom: {}", rom);
+    }
+    error!("last instructions executed:");
+    for (pc, oc) in c8.recent_instructions() {
+        error!("  {:#05x}  {:04x}  {:?}", pc, oc, decode(oc));
+    }
+    match crash::write(c8, speed, error) {
+        Ok(path) => error!("wrote a crash dump to {}", path.display()),
+        Err(e) => warn!("cannot write a crash dump: {}", e),
+    }
+}
 
-    SetRegisterToValue(usize, u8),
-    AddRegisterValue(usize, u8),
+fn init_logging(verbose: u8) {
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or("info"));
+    match verbose {
+        0 => {}
+        1 => {
+            builder.filter_level(LevelFilter::Debug);
+        }
+        _ => {
+            builder.filter_level(LevelFilter::Trace);
+        }
+    }
+    builder.format_timestamp(None).init();
+}
 
-    SetRegister(usize, usize),
-    SetRegisterOR(usize, usize),
-    SetRegisterAND(usize, usize),
-    SetRegisterXOR(usize, usize),
-    AddRegisterToRegister(usize, usize),
-    SubRegisterToRegister85(usize, usize),
-    SubRegisterToRegister87(usize, usize),
-    ShiftRight(usize),
+fn main() {
+    let cli = Cli::parse();
+    init_logging(cli.verbose);
+    match cli.command {
+        Some(Command::Run(args)) => run(*args),
+        Some(Command::Conformance(args)) => conformance::run(args),
+        Some(Command::Verify(args)) => verify::run(args),
+        Some(Command::Trace(args)) => trace::run(args),
+        Some(Command::BisectQuirks(args)) => bisect::run(args),
+        Some(Command::Agent(args)) => agent::run(args),
+        Some(Command::Watch(args)) => {
+            let WatchArgs {
+                keep_state,
+                mut run,
+            } = *args;
+            if run.rom.as_deref().is_none_or(rom::is_stdin) || !run.playlist.is_empty() {
+                error!("watch needs exactly one ROM or source file");
+                exit(2);
+            }
+            run.watch = true;
+            run.keep_state = keep_state;
+            self::run(run)
+        }
+        None => run(cli.run),
+    }
+}
 
-    ShiftLeft(usize),
-    SkipIfRegisterNotEqualRegister(usize, usize),
+fn run(cli: RunArgs) {
+    let file_config = Config::load(cli.config.as_deref()).unwrap_or_else(|e| {
+        error!("{}", e);
+        exit(1);
+    });
+    // settings for the window and the picker, before there is a ROM to
+    // look up overrides for
+    let mut config = file_config.clone();
+    config.merge_args(&cli);
 
-    SetIndex(usize),
+    // the ROM as it is run, with --patch applied
+    let load_rom = |path: &Path| {
+        let (data, symbols) = rom::load_or_exit(path);
+        match &cli.patch {
+            Some(patch) => (rom::patch_or_exit(&data, patch), symbols),
+            None => (data, symbols),
+        }
+    };
 
-    JumpRelV0(usize),
-    RandomAND(usize, u8),
-    Draw(usize, usize, u8),
+    // every instance shares the seed so --compare only shows quirk differences
+    let mut seed = cli.seed.unwrap_or_else(random_seed);
+    let mut netplay = None;
+    if let (Some(rom), true) = (&cli.rom, cli.host.is_some() || cli.join.is_some()) {
+        let rom_config = file_config.for_rom(rom, &cli);
+        let session = netplay::Session {
+            rom: RomId::of(&load_rom(rom).0),
+            quirks: rom_config.quirks,
+            speed: rom_config.speed,
+            font: rom_config.font.unwrap_or_default(),
+            memory_size: rom_config.memory_size.unwrap_or(DEFAULT_MEMORY_SIZE),
+            stack_depth: rom_config.stack_depth.unwrap_or(DEFAULT_STACK_DEPTH),
+            memory_policy: cli.memory_policy,
+            write_protection: cli.write_protect,
+            aligned_pc: cli.aligned_pc,
+            seed,
+        };
+        let connected = match &cli.host {
+            Some(addr) => Netplay::host(addr, &session),
+            None => Netplay::join(cli.join.as_ref().unwrap(), &session),
+        };
+        let (connection, agreed) = connected.unwrap_or_else(|e| {
+            error!("{}", e);
+            exit(1);
+        });
+        seed = agreed;
+        netplay = Some(connection);
+    }
+    info!("seed: {}", seed);
+    let new_machine = |config: &Config, quirks: Quirks, data: &[u8]| {
+        let mut builder = Chip8::builder()
+            .quirks(quirks)
+            .font(config.font.unwrap_or_default())
+            .memory_policy(cli.memory_policy)
+            .write_protection(cli.write_protect)
+            .aligned_pc(cli.aligned_pc)
+            .seed(seed)
+            .count_accesses(cli.heatmap.is_some() || cli.self_modifying)
+            .debug_port(cli.debug_port);
+        if let Some(size) = config.memory_size {
+            builder = builder.memory_size(size);
+        }
+        if let Some(depth) = config.stack_depth {
+            builder = builder.stack_depth(depth);
+        }
+        let mut c8 = builder.build();
+        if let Err(e) = c8.load_rom(data) {
+            error!("{}", e);
+            exit(rom::EXIT_INVALID);
+        }
+        c8
+    };
 
-    SkipIfKey(usize),
-    SkipIfNotKey(usize),
+    if let (Some(addr), Some(rom)) = (&cli.serve, &cli.rom) {
+        let (data, _) = load_rom(rom);
+        info!("loaded {}: {}", rom.display(), RomId::of(&data));
+        let rom_config = file_config.for_rom(rom, &cli);
+        let c8 = new_machine(&rom_config, rom_config.quirks, &data);
+        if !rom::is_stdin(rom) {
+            recent::add(rom);
+        }
+        let limits = Limits::new(cli.max_cycles, cli.exit_after);
+        if let Err(e) = serve::run(addr.as_str(), c8, rom_config.speed, limits) {
+            error!("cannot serve on {}: {}", addr, e);
+            exit(1);
+        }
+        exit(cli.exit_code);
+    }
 
-    SetToDelayTimer(usize),
-    GetKeyPress(usize),
-    SetDelayTimer(usize),
-    SetSoundTimer(usize),
-    AddToIndexRegister(usize),
-    SetIndexToSpriteAddr(usize),
-    BCD(usize),
-    DumpRegistersTill(usize),
-    LoadRegistersTill(usize),
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+    let audio_subsystem = sdl_context.audio().unwrap();
 
-    ClearScreen,
-    Return,
-    Noop,
-}
+    let instances = if cli.compare.is_some() { 2 } else { 1 };
+    let scale = config.scale as usize;
+    let window = video_subsystem
+        .window(
+            "rust-sdl2 demo",
+            (WIDTH * scale * instances) as u32,
+            (HEIGHT * scale) as u32 + if cli.hud { hud::HEIGHT } else { 0 },
+        )
+        .position_centered()
+        .build()
+        .unwrap_or_else(|e| {
+            error!("cannot open a window: {}", e);
+            exit(1);
+        });
+    let mut canvas = window.into_canvas().build().unwrap();
 
-struct Chip8 {
-    memory: Vec<u8>,
-    registers: Vec<u8>,
-    index: usize, // index register
-    pc: usize,    // program counter
-    pixel_buffer: Vec<Vec<bool>>,
-    delay_timer: u8,
-    sound_timer: u8,
+    canvas.set_draw_color(Color::from(config.palette.background));
+    canvas.clear();
 
-    call_stack: Vec<usize>,
-    keypad: Vec<bool>,
+    let desired_spec = AudioSpecDesired {
+        freq: Some(44100),
+        channels: Some(1), // mono
+        samples: None,     // default sample size
+    };
 
-    canvas: Canvas<Window>,
-    audio_device: AudioDevice<SquareWave>,
-}
+    let device = if config.audio.enabled {
+        let device = audio_subsystem
+            .open_playback(None, &desired_spec, |spec| {
+                // initialize the audio callback
+                SquareWave {
+                    phase_inc: config.audio.tone / spec.freq as f32,
+                    phase: 0.0,
+                    volume: config.audio.volume,
+                }
+            })
+            .unwrap();
+        Some(device)
+    } else {
+        None
+    };
+    canvas.present();
+    let mut event_pump = sdl_context.event_pump().unwrap();
 
-impl Chip8 {
-    fn new(canvas: Canvas<Window>, audio_device: AudioDevice<SquareWave>) -> Self {
-        let mut c8 = Chip8 {
-            memory: vec![0; 4096],  // 4k memory
-            registers: vec![0; 16], // 16 8-bit registers
-            index: 0,
-            pc: 0x200,                               // program counter starts at 0x200
-            pixel_buffer: vec![vec![false; 64]; 32], // 2048 pixels
-            delay_timer: 0,
-            sound_timer: 0,
-
-            call_stack: vec![0; 16],
-            keypad: vec![false; 16],
-            canvas,
-            audio_device,
-        };
+    // a baked in ROM goes by its original file name for config sections,
+    // save states and so on
+    let embedded =
+        cli.rom.is_none() && !cli.recent && cli.playlist.is_empty() && !EMBEDDED_ROM.is_empty();
+    let playlist = if cli.playlist.is_empty() {
+        vec![match cli.rom.clone() {
+            Some(rom) => rom,
+            None if embedded => PathBuf::from(EMBEDDED_ROM_NAME),
+            None if cli.recent => recent::load().into_iter().next().unwrap_or_else(|| {
+                error!("no recently played ROMs");
+                exit(1);
+            }),
+            None => {
+                // recently played ones first, without listing them twice
+                let mut roms = recent::load();
+                let recent = roms.len();
+                match picker::scan(&cli.rom_dir) {
+                    Ok(found) => {
+                        for rom in found {
+                            let absolute = fs::canonicalize(&rom).unwrap_or_else(|_| rom.clone());
+                            if !roms[..recent].contains(&absolute) {
+                                roms.push(rom);
+                            }
+                        }
+                    }
+                    Err(e) if recent == 0 => {
+                        error!("cannot read {}: {}", cli.rom_dir.display(), e);
+                        exit(1);
+                    }
+                    Err(e) => warn!("cannot read {}: {}", cli.rom_dir.display(), e),
+                }
+                if roms.is_empty() {
+                    error!("no ROMs found in {}", cli.rom_dir.display());
+                    exit(1);
+                }
+                match picker::pick(&mut canvas, &mut event_pump, &cli.rom_dir, &roms, recent) {
+                    Some(rom) => rom,
+                    None => return,
+                }
+            }
+        }]
+        .into_iter()
+        .map(|rom| {
+            // a zip with several ROMs asks which one
+            if !archive::is_zip(&rom) || !rom.is_file() {
+                return rom;
+            }
+            let roms = match archive::roms(&rom) {
+                Ok(roms) if roms.len() > 1 => roms,
+                _ => return rom,
+            };
+            let roms: Vec<PathBuf> = roms.iter().map(|entry| rom.join(entry)).collect();
+            picker::pick(&mut canvas, &mut event_pump, &rom, &roms, 0).unwrap_or_else(|| exit(0))
+        })
+        .collect()
+    } else {
+        picker::expand(&cli.playlist).unwrap_or_else(|e| {
+            error!("playlist: {}", e);
+            exit(1);
+        })
+    };
 
-        c8.load_fonts();
-        c8
+    #[cfg(feature = "scripting")]
+    let mut script = cli.script.as_deref().map(|path| {
+        script::Script::load(path).unwrap_or_else(|e| {
+            error!("{}", e);
+            exit(1);
+        })
+    });
+    if cli.console && cli.rom.as_deref().is_some_and(rom::is_stdin) {
+        error!("--console reads commands from stdin, it cannot be used with a ROM from stdin");
+        exit(2);
     }
+    let mut console = if cli.console {
+        Some(Console::new())
+    } else {
+        None
+    };
+    let mut rumble = if cli.rumble {
+        Some(Rumble::new(&sdl_context).unwrap_or_else(|e| {
+            error!("cannot initialise rumble: {}", e);
+            exit(1);
+        }))
+    } else {
+        None
+    };
+    let mut spectators = cli.spectate.as_ref().map(|addr| {
+        serve::Server::bind(addr.as_str(), &Chip8::new(), true).unwrap_or_else(|e| {
+            error!("cannot serve on {}: {}", addr, e);
+            exit(1);
+        })
+    });
+    let mut sprite_viewer = cli.sprite_viewer.map(|range| {
+        sprites::SpriteViewer::new(&video_subsystem, range).unwrap_or_else(|e| {
+            error!("cannot open the sprite viewer: {}", e);
+            exit(1);
+        })
+    });
+    let mut hud = cli.hud.then(Hud::default);
+    let mut coverage = Coverage::default();
+    let mut telemetry = cli.telemetry.as_ref().map(|_| Telemetry::new());
+    let mut telemetry_written = Instant::now();
+    let mut status = 0;
+    let mut limits = Limits::new(cli.max_cycles, cli.exit_after);
 
-    fn load_fonts(&mut self) {
-        let chip8_fontset: [u8; 80] = [
-            0b11110000, 0b10010000, 0b10010000, 0b10010000, 0b11110000, // Zero
-            0b00100000, 0b01100000, 0b00100000, 0b00100000, 0b01110000, // One
-            0b11110000, 0b00010000, 0b11110000, 0b10000000, 0b11110000, // Two
-            0b11110000, 0b00010000, 0b11110000, 0b00010000, 0b11110000, // Three
-            0b10010000, 0b10010000, 0b11110000, 0b00010000, 0b00010000, // Four
-            0b11110000, 0b10000000, 0b11110000, 0b00010000, 0b11110000, // Five
-            0b11110000, 0b10000000, 0b11110000, 0b10010000, 0b11110000, // Six
-            0b11110000, 0b00010000, 0b00100000, 0b01000000, 0b01000000, // Seven
-            0b11110000, 0b10010000, 0b11110000, 0b10010000, 0b11110000, // Eight
-            0b11110000, 0b10010000, 0b11110000, 0b00010000, 0b11110000, // Nine
-            0b11110000, 0b10010000, 0b11110000, 0b10010000, 0b10010000, // A
-            0b11100000, 0b10010000, 0b11100000, 0b10010000, 0b11100000, // B
-            0b11110000, 0b10000000, 0b10000000, 0b10000000, 0b11110000, // C
-            0b11100000, 0b10010000, 0b10010000, 0b10010000, 0b11100000, // D
-            0b11110000, 0b10000000, 0b11110000, 0b10000000, 0b11110000, // E
-            0b11110000, 0b10000000, 0b11110000, 0b10000000, 0b10000000, // F
-        ];
-
-        self.memory[0x50..0xA0].copy_from_slice(&chip8_fontset);
-    }
+    let mut watcher = cli.watch.then(|| watch::Watcher::new(&playlist[0]));
+    // a rebuilt ROM, and the machines to carry over with --keep-state
+    let mut reloaded: Option<(Vec<u8>, Symbols)> = None;
+    let mut carried: Vec<Chip8> = Vec::new();
+    let mut start_state = cli.load_state.as_ref().map(|path| {
+        crash::read_state(path).unwrap_or_else(|e| {
+            error!("{}", e);
+            exit(1);
+        })
+    });
+
+    let mut current = 0;
+    loop {
+        let file_path = playlist[current].clone();
+        let config = file_config.for_rom(&file_path, &cli);
+        let keys = config.keys().unwrap_or_else(|e| {
+            error!("keymap: {}", e);
+            exit(1);
+        });
+        let mut quirk_sets = vec![config.quirks];
+        quirk_sets.extend(cli.compare);
 
-    fn decode(&mut self, oc: Opcode) -> Instruction {
-        let reg1: usize = ((oc & 0x0F00) >> 8) as usize;
-        let reg2: usize = ((oc & 0x00F0) >> 4) as usize;
-        let nnn: usize = (oc & 0x0FFF) as usize;
-        let nn: u8 = (oc & 0x00FF) as u8;
-        let n: u8 = (oc & 0x000F) as u8;
-
-        return match oc & 0xF000 {
-            0x0000 => match oc & 0x00FF {
-                0x00E0 => Instruction::ClearScreen,
-                0x00EE => Instruction::Return,
-                _ => Instruction::Noop,
-            },
-            0x1000 => Instruction::JumpTo(nnn),
-            0x2000 => Instruction::Subroutine(nnn),
-
-            0x3000 => Instruction::SkipIfRegisterEqualValue(reg1, nn),
-            0x4000 => Instruction::SkipIfRegisterNotEqualValue(reg1, nn),
-
-            0x5000 => Instruction::SkipIfRegisterEqualRegister(reg1, reg2),
-
-            0x6000 => Instruction::SetRegisterToValue(reg1, nn),
-
-            0x7000 => Instruction::AddRegisterValue(reg1, nn),
-
-            0x8000 => match oc & 0x000F {
-                0x0000 => Instruction::SetRegister(reg1, reg2),
-                0x0001 => Instruction::SetRegisterOR(reg1, reg2),
-                0x0002 => Instruction::SetRegisterAND(reg1, reg2),
-                0x0003 => Instruction::SetRegisterXOR(reg1, reg2),
-                0x0004 => Instruction::AddRegisterToRegister(reg1, reg2),
-                0x0005 => Instruction::SubRegisterToRegister85(reg1, reg2),
-                0x0006 => Instruction::ShiftRight(reg1),
-                0x0007 => Instruction::SubRegisterToRegister87(reg1, reg2),
-                0x000E => Instruction::ShiftLeft(reg1),
-                _ => Instruction::Noop,
-            },
-
-            0x9000 => Instruction::SkipIfRegisterNotEqualRegister(reg1, reg2),
-            0xA000 => Instruction::SetIndex(nnn),
-            0xB000 => Instruction::JumpRelV0(nnn),
-            0xC000 => Instruction::RandomAND(reg1, nn),
-
-            0xD000 => Instruction::Draw(reg1, reg2, n),
-
-            0xE000 => match oc & 0x00FF {
-                0x009E => Instruction::SkipIfKey(reg1),
-                0x00A1 => Instruction::SkipIfNotKey(reg1),
-                _ => Instruction::Noop,
-            },
-
-            0xF000 => match oc & 0x00FF {
-                0x0007 => Instruction::SetToDelayTimer(reg1),
-                0x000A => Instruction::GetKeyPress(reg1),
-                0x0015 => Instruction::SetDelayTimer(reg1),
-                0x0018 => Instruction::SetSoundTimer(reg1),
-                0x001E => Instruction::AddToIndexRegister(reg1),
-                0x0029 => Instruction::SetIndexToSpriteAddr(reg1),
-                0x0033 => Instruction::BCD(reg1),
-                0x0055 => Instruction::DumpRegistersTill(reg1),
-                0x0065 => Instruction::LoadRegistersTill(reg1),
-                _ => Instruction::Noop,
-            },
-
-            _ => Instruction::Noop,
+        canvas.set_draw_color(Color::from(config.palette.background));
+        canvas.clear();
+        canvas.present();
+
+        let (data, symbols) = if embedded {
+            (EMBEDDED_ROM.to_vec(), Symbols::default())
+        } else if let Some(reloaded) = reloaded.take() {
+            reloaded
+        } else {
+            load_rom(&file_path)
         };
-    }
+        info!("loaded {}: {}", file_path.display(), RomId::of(&data));
+        if let Some(telemetry) = telemetry.as_mut() {
+            telemetry.set_rom(&RomId::of(&data));
+        }
+        if !embedded && !rom::is_stdin(&file_path) {
+            recent::add(&file_path);
+        }
 
-    fn execute(&mut self, instruction: Instruction) {
-        match instruction {
-            Instruction::ClearScreen => {
-                self.clear_screen();
-                self.pc += 2;
-            }
-            Instruction::Return => {
-                let ret_addr = self.call_stack.pop().unwrap();
-                self.pc = ret_addr;
-            }
-            Instruction::JumpTo(addr) => self.pc = addr,
-            Instruction::Subroutine(addr) => {
-                self.call_stack.push(self.pc + 2);
-                self.pc = addr;
+        // this should wait for a keypress and then put a character on the screen
+        // let mut data: Vec<u8> = vec![
+        //    0xF1, 0x0A, // wait for key press
+        //    0x00, 0xE0, // clear the screen
+        //    0x61, 0x03, // set v1 to 05
+        //    0xF1, 0x29, // set i to location of character in v1
+        //    0x61, 0x38, // set v1 to 38
+        //    0x62, 0x00, // set v2 to 00
+        //    0xD1, 0x25, // draw at location in v1 and v2 for height of 5
+        //    0x00, 0x0F,
+        //    0x12, 0x0C, // jump to address 20c
+        // ];
+
+        // what the cheats, splits and persisted memory have to fit in
+        let memory_len = config.memory_size.unwrap_or(DEFAULT_MEMORY_SIZE);
+        let cheat_path = cli.cheats.clone().or_else(|| paths::cheat_file(&file_path));
+        let mut cheats = match &cheat_path {
+            Some(path) => {
+                Cheats::load(path, cli.cheats.is_some(), memory_len).unwrap_or_else(|e| {
+                    error!("{}", e);
+                    exit(1);
+                })
             }
-            Instruction::SkipIfRegisterEqualValue(reg, val) => {
-                self.pc += 2;
-                if self.registers[reg] == val {
-                    self.pc += 2;
-                }
+            None => Cheats::default(),
+        };
+        if !cheats.is_empty() {
+            info!(
+                "{} cheats from {}",
+                cheats.len(),
+                cheat_path.as_ref().unwrap().display()
+            );
+        }
+
+        if let Some(console) = console.as_mut() {
+            console.set_cheat_path(cheat_path);
+            console.set_symbols(symbols.clone());
+        }
+
+        let mut macros = Macros::load(paths::macro_file(&file_path));
+        let mut self_modifying = SelfModifying::default();
+        let mut speedrun = cli.splits.as_ref().map(|path| {
+            Speedrun::load(path, memory_len, paths::splits_log(&file_path)).unwrap_or_else(|e| {
+                error!("{}", e);
+                exit(1);
+            })
+        });
+        let mut persist = config.persist.and_then(|range| {
+            let path = paths::persist_file(&file_path)?;
+            Some(Persist::new(range, path, memory_len).unwrap_or_else(|e| {
+                error!("{}", e);
+                exit(1);
+            }))
+        });
+
+        let new_machines = |cheats: &Cheats, persist: Option<&Persist>| -> Vec<Chip8> {
+            quirk_sets
+                .iter()
+                .map(|&quirks| {
+                    let mut c8 = new_machine(&config, quirks, &data);
+                    if let Some(persist) = persist {
+                        persist.restore(&mut c8);
+                    }
+                    cheats.apply(&mut c8);
+                    c8
+                })
+                .collect()
+        };
+        let mut machines = new_machines(&cheats, persist.as_ref());
+        if !carried.is_empty() {
+            // the ROM was checked to fit when it was rebuilt
+            carried
+                .iter_mut()
+                .for_each(|c8| c8.load_rom(&data).unwrap());
+            machines = std::mem::take(&mut carried);
+            rerender(
+                &mut canvas,
+                &machines,
+                &config,
+                hud.as_ref(),
+                speedrun.as_ref(),
+            );
+        }
+        if let Some(state) = start_state.take() {
+            if let Err(e) = machines.iter_mut().try_for_each(|c8| c8.load_state(&state)) {
+                error!(
+                    "cannot load {}: {:?}",
+                    cli.load_state.as_ref().unwrap().display(),
+                    e
+                );
+                exit(1);
             }
-            Instruction::SkipIfRegisterNotEqualValue(reg, val) => {
-                self.pc += 2;
-                if self.registers[reg] != val {
-                    self.pc += 2;
-                }
+            rerender(
+                &mut canvas,
+                &machines,
+                &config,
+                hud.as_ref(),
+                speedrun.as_ref(),
+            );
+        }
+        let new_movie = || {
+            let rom = RomId::of(&data);
+            cli.record_movie
+                .as_ref()
+                .map(|_| Movie::new(&rom, config.quirks, config.speed, seed))
+        };
+        let mut movie = new_movie();
+        if machines.len() > 1 {
+            info!(
+                "comparing quirks [{}] (left) with [{}] (right)",
+                quirk_sets[0], quirk_sets[1]
+            );
+        }
+        let mut frame: u64 = 0;
+        if let Some(hud) = hud.as_mut() {
+            hud.clear();
+        }
+        let mut diverged = false;
+        // both sides have to keep running in step
+        let mut paused = config.start_paused && netplay.is_none();
+        // run a single frame while paused
+        let mut advance = false;
+        let mut next_frame = Instant::now();
+        let started = Instant::now();
+        let mut next = false;
+
+        'running: loop {
+            if limits.reached() {
+                status = cli.exit_code;
+                break 'running;
             }
-            Instruction::SkipIfRegisterEqualRegister(reg1, reg2) => {
-                self.pc += 2;
-                if self.registers[reg1] == self.registers[reg2] {
-                    self.pc += 2;
+            let frame_started = Instant::now();
+            let mut render = Duration::ZERO;
+            for event in event_pump.poll_iter() {
+                match event {
+                    Event::Quit { .. } => break 'running,
+                    // with the sprite viewer open there is no Quit until both
+                    // windows are closed
+                    Event::Window {
+                        win_event: WindowEvent::Close,
+                        window_id,
+                        ..
+                    } => match &sprite_viewer {
+                        Some(viewer) if viewer.window_id() == window_id => sprite_viewer = None,
+                        _ => break 'running,
+                    },
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.quit => break 'running,
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.next && playlist.len() > 1 => {
+                        next = true;
+                        break 'running;
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.record_macro => macros.toggle_recording(frame),
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.play_macro => macros.play(frame),
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.pause => {
+                        if netplay.is_some() {
+                            warn!("not available during netplay");
+                            continue;
+                        }
+                        paused = !paused;
+                        info!("{}", if paused { "paused" } else { "resumed" });
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.frame_advance && paused => advance = true,
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.reset => {
+                        if netplay.is_some() {
+                            warn!("not available during netplay");
+                            continue;
+                        }
+                        machines = new_machines(&cheats, persist.as_ref());
+                        movie = new_movie();
+                        frame = 0;
+                        diverged = false;
+                        if let Some(hud) = hud.as_mut() {
+                            hud.clear();
+                        }
+                        if let Some(speedrun) = speedrun.as_mut() {
+                            speedrun.reset();
+                        }
+                        rerender(
+                            &mut canvas,
+                            &machines,
+                            &config,
+                            hud.as_ref(),
+                            speedrun.as_ref(),
+                        );
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.save_state => {
+                        if let Some(path) = paths::state_file(&file_path) {
+                            match fs::write(&path, machines[0].save_state()) {
+                                Ok(()) => info!("saved state to {}", path.display()),
+                                Err(e) => error!("cannot write {}: {}", path.display(), e),
+                            }
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.load_state => {
+                        if netplay.is_some() {
+                            warn!("not available during netplay");
+                            continue;
+                        }
+                        if let Some(path) = paths::state_file(&file_path) {
+                            let loaded =
+                                fs::read(&path)
+                                    .map_err(|e| e.to_string())
+                                    .and_then(|state| {
+                                        // every instance restores the same state
+                                        machines
+                                            .iter_mut()
+                                            .try_for_each(|c8| c8.load_state(&state))
+                                            .map_err(|e| format!("{:?}", e))
+                                    });
+                            match loaded {
+                                Ok(()) => {
+                                    info!("loaded state from {}", path.display());
+                                    rerender(
+                                        &mut canvas,
+                                        &machines,
+                                        &config,
+                                        hud.as_ref(),
+                                        speedrun.as_ref(),
+                                    );
+                                }
+                                Err(e) => error!("cannot load {}: {}", path.display(), e),
+                            }
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if key == keys.screenshot => {
+                        let taken = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |t| t.as_secs());
+                        if let Some(path) = paths::screenshot_file(&file_path, taken) {
+                            match screenshot::save(
+                                &machines[0],
+                                &config.palette,
+                                config.scale,
+                                &path,
+                            ) {
+                                Ok(()) => info!("saved screenshot to {}", path.display()),
+                                Err(e) => error!("cannot write {}: {}", path.display(), e),
+                            }
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key), ..
+                    } => {
+                        if let Some(&k) = keys.keypad.get(&key) {
+                            machines.iter_mut().for_each(|c8| c8.set_key(k, true));
+                            #[cfg(feature = "scripting")]
+                            if let Some(script) = script.as_mut() {
+                                script.on_key(&mut machines[0], k, true);
+                            }
+                        }
+                    }
+                    Event::ControllerDeviceAdded { which, .. } => {
+                        if let Some(rumble) = rumble.as_mut() {
+                            rumble.connect(which);
+                        }
+                    }
+                    Event::ControllerDeviceRemoved { .. } => {
+                        if let Some(rumble) = rumble.as_mut() {
+                            rumble.rescan();
+                        }
+                    }
+                    Event::KeyUp {
+                        keycode: Some(key), ..
+                    } => {
+                        if let Some(&k) = keys.keypad.get(&key) {
+                            machines.iter_mut().for_each(|c8| c8.set_key(k, false));
+                            #[cfg(feature = "scripting")]
+                            if let Some(script) = script.as_mut() {
+                                script.on_key(&mut machines[0], k, false);
+                            }
+                        }
+                    }
+                    _ => {}
                 }
             }
-            Instruction::SetRegisterToValue(reg, val) => {
-                self.pc += 2;
-                self.registers[reg] = val;
-            }
-            Instruction::AddRegisterValue(reg, val) => {
-                self.pc += 2;
-                self.registers[reg] = self.registers[reg].wrapping_add(val);
-            }
-            Instruction::SetRegister(reg1, reg2) => {
-                self.pc += 2;
-                self.registers[reg1] = self.registers[reg2];
-            }
-            Instruction::SetRegisterOR(reg1, reg2) => {
-                self.pc += 2;
-                self.registers[reg1] |= self.registers[reg2];
-            }
-            Instruction::SetRegisterAND(reg1, reg2) => {
-                self.pc += 2;
-                self.registers[reg1] &= self.registers[reg2];
-            }
-            Instruction::SetRegisterXOR(reg1, reg2) => {
-                self.pc += 2;
-                self.registers[reg1] ^= self.registers[reg2];
-            }
-            Instruction::AddRegisterToRegister(reg1, reg2) => {
-                self.pc += 2;
-                self.registers[reg1] = self.registers[reg1].wrapping_add(self.registers[reg2]);
-                let (_, overflow) = self.registers[reg1].overflowing_add(self.registers[reg2]);
-                // self.registers[reg1] = res;
-                self.registers[0xF] = if overflow { 1 } else { 0 };
-            }
-            Instruction::SubRegisterToRegister85(reg1, reg2) => {
-                self.pc += 2;
-                let vx = self.registers[reg1];
-                let vy = self.registers[reg2];
 
-                self.registers[15] = if vx >= vy { 1 } else { 0 }; // borrow does not occur
-                self.registers[reg1] = vx.wrapping_sub(vy);
+            let running = !paused || advance;
+            advance = false;
+            if running {
+                macros.update(frame, &mut machines);
             }
-            Instruction::SubRegisterToRegister87(reg1, reg2) => {
-                self.pc += 2;
-                let vx = self.registers[reg1];
-                let vy = self.registers[reg2];
 
-                self.registers[15] = if vx <= vy { 1 } else { 0 }; // borrow does not occur
-                self.registers[reg1] = vy.wrapping_sub(vx);
-            }
-            Instruction::ShiftRight(reg) => {
-                self.pc += 2;
-                let vx = self.registers[reg];
-                self.registers[15] = vx & 1;
-                self.registers[reg] = vx >> 1;
-            }
-            Instruction::ShiftLeft(reg) => {
-                self.pc += 2;
-                let vx = self.registers[reg];
-                self.registers[15] = vx >> 7;
-                self.registers[reg] = vx << 1;
-            }
-            Instruction::SkipIfRegisterNotEqualRegister(reg1, reg2) => {
-                self.pc += 2;
-                if self.registers[reg1] != self.registers[reg2] {
-                    self.pc += 2;
+            if let Some(netplay) = netplay.as_mut() {
+                if let Err(e) = netplay.exchange(&mut machines[0]) {
+                    error!("{}", e);
+                    status = 1;
+                    break 'running;
                 }
             }
-            Instruction::SetIndex(addr) => {
-                self.index = addr;
-                self.pc += 2;
-            }
-            Instruction::JumpRelV0(val) => {
-                self.pc = val.wrapping_add(self.registers[0] as usize);
-            }
-            Instruction::RandomAND(reg, val) => {
-                self.pc += 2;
-                let random_byte: u8 = random();
-                self.registers[reg] = random_byte & val;
-            }
-            Instruction::Draw(reg1, reg2, height) => {
-                self.pc += 2;
-                let x = self.registers[reg1] as usize;
-                let y = self.registers[reg2] as usize;
-                self.registers[15] = 0;
-
-                let mut did_overflow: bool = false;
-
-                for i in 0usize..(height as usize) {
-                    let word = self.memory[self.index + i];
-                    for j in 0usize..8 {
-                        let tx = (x + j) % 64;
-                        let ty = (y + i) % 32;
-                        if word & (0x80 >> j) != 0 {
-                            if self.pixel_buffer[ty][tx] == true {
-                                did_overflow = true;
+
+            if running {
+                if let Some(movie) = movie.as_mut() {
+                    movie.record(frame, &machines[0]);
+                }
+                let mut redraw = false;
+                for _ in 0..config.speed {
+                    for (i, c8) in machines.iter_mut().enumerate() {
+                        if let Err(e) = c8.tick_cpu() {
+                            report_crash(c8, config.speed, e);
+                            if let Some(label) = symbols.describe(c8.pc()) {
+                                error!("pc {:#05x} is at {} in the source", c8.pc(), label);
                             }
-                            self.pixel_buffer[ty][tx] = !self.pixel_buffer[ty][tx];
+                            status = 1;
+                            break 'running;
+                        }
+                        if i == 0 && (cli.coverage || cli.coverage_addresses) {
+                            coverage.record(c8);
+                        }
+                        if let (0, Some(telemetry)) = (i, telemetry.as_mut()) {
+                            telemetry.record_instruction(c8);
+                        }
+                        #[cfg(feature = "scripting")]
+                        if let (0, Some(script)) = (i, script.as_mut()) {
+                            script.on_instruction(c8);
                         }
+                        redraw |= c8.take_draw_flag();
                     }
+                    limits.count();
+                    if limits.reached() {
+                        status = cli.exit_code;
+                        break 'running;
+                    }
+                }
+                machines.iter_mut().for_each(|c8| {
+                    c8.tick_60hz();
+                    cheats.refresh(c8);
+                });
+                if cli.self_modifying {
+                    self_modifying.log(&mut machines[0], &symbols);
+                    machines[1..]
+                        .iter_mut()
+                        .for_each(|c8| drop(c8.take_code_writes()));
                 }
+                if cli.debug_port.is_some() {
+                    // --compare instances print the same, only show the first
+                    let output = machines[0].take_debug_output();
+                    machines[1..]
+                        .iter_mut()
+                        .for_each(|c8| drop(c8.take_debug_output()));
+                    let mut stdout = io::stdout();
+                    if let Err(e) = stdout.write_all(&output).and_then(|()| stdout.flush()) {
+                        warn!("cannot write debug output: {}", e);
+                    }
+                }
+                #[cfg(feature = "scripting")]
+                if let Some(script) = script.as_mut() {
+                    script.on_frame(&mut machines[0]);
+                }
+                frame += 1;
+                if let Some(speedrun) = speedrun.as_mut() {
+                    speedrun.update(frame, &machines[0]);
+                }
+                if let (Some(persist), 0) = (persist.as_mut(), frame % 60) {
+                    persist.flush(&machines[0]);
+                }
+                debug!(
+                    "frame {}: pc {:#05x}, delay {}, sound {}, redraw {}",
+                    frame,
+                    machines[0].pc(),
+                    machines[0].delay_timer(),
+                    machines[0].sound_timer(),
+                    redraw
+                );
 
-                self.registers[15] = if did_overflow { 1 } else { 0 };
-                self.rerender();
-            }
-            Instruction::SkipIfKey(reg) => {
-                self.pc += 2;
-                if self.keypad[self.registers[reg] as usize] {
-                    self.pc += 2;
+                if !diverged
+                    && machines.len() > 1
+                    && machines[0].pixel_buffer() != machines[1].pixel_buffer()
+                {
+                    diverged = true;
+                    warn!(
+                        "framebuffers diverged at frame {} (left pc {:#05x}, right pc {:#05x})",
+                        frame,
+                        machines[0].pc(),
+                        machines[1].pc()
+                    );
                 }
-            }
-            Instruction::SkipIfNotKey(reg) => {
-                self.pc += 2;
-                if !self.keypad[self.registers[reg] as usize] {
-                    self.pc += 2;
+                if let Some(hud) = hud.as_mut() {
+                    hud.record(&machines[0]);
                 }
-            }
-            Instruction::SetToDelayTimer(reg) => {
-                self.pc += 2;
-                self.registers[reg] = self.delay_timer;
-            }
-            Instruction::GetKeyPress(reg) => {
-                for i in 0..16 {
-                    if self.keypad[i] {
-                        self.pc += 2;
-                        self.registers[reg] = i as u8;
-                        return;
-                    }
+                // the graph and the timer move every frame
+                if redraw || hud.is_some() || speedrun.is_some() {
+                    let render_started = Instant::now();
+                    rerender(
+                        &mut canvas,
+                        &machines,
+                        &config,
+                        hud.as_ref(),
+                        speedrun.as_ref(),
+                    );
+                    render = render_started.elapsed();
+                }
+                if let Some(telemetry) = telemetry.as_mut() {
+                    let beeping = machines[0].sound_timer() > 0;
+                    telemetry.record_frame(frame_started.elapsed(), redraw, beeping);
                 }
             }
-            Instruction::SetDelayTimer(reg) => {
-                self.pc += 2;
-                self.delay_timer = self.registers[reg];
+
+            if let Some(console) = console.as_mut() {
+                console.update(&mut machines[0], &mut cheats);
             }
-            Instruction::SetSoundTimer(reg) => {
-                self.pc += 2;
-                self.sound_timer = self.registers[reg];
+
+            if let Some(viewer) = sprite_viewer.as_mut() {
+                viewer.update(&machines[0]);
+            }
+
+            if let Some(server) = spectators.as_mut() {
+                if let Err(e) = server.poll(&mut machines[0]) {
+                    warn!("stopped streaming to spectators: {}", e);
+                    spectators = None;
+                } else {
+                    server.update(&machines[0]);
+                }
             }
-            Instruction::AddToIndexRegister(reg) => {
-                self.pc += 2;
-                self.index += self.registers[reg] as usize;
-                self.registers[15] = if self.index > 0x0FFF { 1 } else { 0 };
+
+            let beeping = !paused && machines[0].sound_timer() > 0;
+            if let Some(device) = &device {
+                if beeping {
+                    device.resume();
+                } else {
+                    device.pause();
+                }
             }
-            Instruction::SetIndexToSpriteAddr(reg) => {
-                self.pc += 2;
-                let vx = self.registers[reg];
-                self.index = 0x50 + (5 * vx as usize);
+            if let Some(rumble) = rumble.as_mut() {
+                rumble.update(beeping);
             }
-            Instruction::BCD(reg) => {
-                self.pc += 2;
-                let vx = self.registers[reg];
-                self.memory[self.index] = vx / 100;
-                self.memory[self.index + 1] = (vx / 10) % 10;
-                self.memory[self.index + 2] = vx % 10;
+
+            if let (Some(telemetry), Some(path), Some(interval)) =
+                (telemetry.as_mut(), &cli.telemetry, cli.telemetry_interval)
+            {
+                if telemetry_written.elapsed() >= Duration::from_secs(interval) {
+                    if let Err(e) = telemetry.write(path) {
+                        warn!("{}", e);
+                    }
+                    telemetry_written = Instant::now();
+                }
             }
-            Instruction::DumpRegistersTill(reg) => {
-                self.pc += 2;
-                for i in 0..=(reg as u8) {
-                    self.memory[self.index + (i as usize)] = self.registers[i as usize];
+
+            if let Some(watcher) = watcher.as_mut() {
+                if watcher.changed() {
+                    let memory_size = config.memory_size.unwrap_or(DEFAULT_MEMORY_SIZE);
+                    match watch::build(&file_path, memory_size) {
+                        Ok(build) => {
+                            info!("{} changed, reloading", file_path.display());
+                            reloaded = Some(build);
+                            next = true;
+                            break 'running;
+                        }
+                        Err(e) => error!("{}", e),
+                    }
                 }
             }
-            Instruction::LoadRegistersTill(reg) => {
-                self.pc += 2;
-                for i in 0..=(reg as u8) {
-                    self.registers[i as usize] = self.memory[self.index + (i as usize)];
+
+            if let Some(interval) = cli.playlist_interval {
+                if playlist.len() > 1 && started.elapsed() >= Duration::from_secs(interval) {
+                    next = true;
+                    break 'running;
                 }
             }
 
-            _ => {}
+            let now = wait_for_frame(&mut next_frame);
+            let work = now - frame_started;
+            if let Some(hud) = hud.as_mut() {
+                hud.record_times(FrameTimes {
+                    emulate: work.saturating_sub(render),
+                    render,
+                    sleep: now.elapsed(),
+                    total: frame_started.elapsed(),
+                });
+            }
         }
-    }
 
-    fn rerender(&mut self) {
-        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
-        self.canvas.clear();
-        self.canvas.set_draw_color(Color::RGB(255, 255, 255));
-        for y in 0..32 {
-            for x in 0..64 {
-                if self.pixel_buffer[y][x] {
-                    self.canvas
-                        .fill_rect(Rect::new((x * 10) as i32, (y * 10) as i32, 10, 10))
-                        .unwrap();
-                }
+        if let (Some(movie), Some(path)) = (movie.as_mut(), &cli.record_movie) {
+            movie.record(frame, &machines[0]);
+            match movie.save(path) {
+                Ok(()) => info!("recorded {} frames to {}", frame, path.display()),
+                Err(e) => error!("{}", e),
             }
         }
-        self.canvas.present();
-    }
-
-    fn handle_key_press(&mut self, event: EventType, key: Keycode) {
-        // the qwerty keys are mapped in the following manner
-        // Keypad                   QWERTY
-        // +-+-+-+-+                +-+-+-+-+
-        // |1|2|3|C|                |1|2|3|4|
-        // +-+-+-+-+                +-+-+-+-+
-        // |4|5|6|D|                |Q|W|E|R|
-        // +-+-+-+-+       =>       +-+-+-+-+
-        // |7|8|9|E|                |A|S|D|F|
-        // +-+-+-+-+                +-+-+-+-+
-        // |A|0|B|F|                |Z|X|C|V|
-        // +-+-+-+-+                +-+-+-+-+
-        let value = match event {
-            EventType::KeyDown { .. } => Some(true),
-            EventType::KeyUp { .. } => Some(false),
-            _ => None,
+        if let Some(persist) = persist.as_mut() {
+            persist.flush(&machines[0]);
         }
-        .unwrap();
-
-        match key {
-            Keycode::Num1 => self.keypad[0x1] = value,
-            Keycode::Num2 => self.keypad[0x2] = value,
-            Keycode::Num3 => self.keypad[0x3] = value,
-            Keycode::Q => self.keypad[0x4] = value,
-            Keycode::W => self.keypad[0x5] = value,
-            Keycode::E => self.keypad[0x6] = value,
-            Keycode::A => self.keypad[0x7] = value,
-            Keycode::S => self.keypad[0x8] = value,
-            Keycode::D => self.keypad[0x9] = value,
-            Keycode::X => self.keypad[0x0] = value,
-            Keycode::Z => self.keypad[0xa] = value,
-            Keycode::C => self.keypad[0xb] = value,
-            Keycode::Num4 => self.keypad[0xc] = value,
-            Keycode::R => self.keypad[0xd] = value,
-            Keycode::F => self.keypad[0xe] = value,
-            Keycode::V => self.keypad[0xf] = value,
-            _ => {}
+        if let (Some(accesses), Some(path)) = (machines[0].accesses(), &cli.heatmap) {
+            match heatmap::save(accesses, path) {
+                Ok(()) => info!("saved memory heatmap to {}", path.display()),
+                Err(e) => error!("cannot save {}: {}", path.display(), e),
+            }
         }
-    }
-
-    fn clear_screen(&mut self) {
-        self.pixel_buffer = vec![vec![false; 64]; 32];
-        self.rerender()
-    }
-
-    fn fetch(&self) -> Opcode {
-        (self.memory[self.pc] as u16) << 8 | (self.memory[self.pc + 1] as u16)
-    }
-
-    fn update_delay_timer(&mut self) {
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
+        if cli.self_modifying {
+            self_modifying.summary();
         }
-    }
-
-    fn update_sound_timer(&mut self) {
-        if self.sound_timer > 0 {
-            self.audio_device.resume();
-            self.sound_timer -= 1;
+        if !next {
+            break;
         }
-        self.audio_device.pause();
-    }
-
-    fn sleep() {
-        thread::sleep(time::Duration::from_millis(5));
+        if reloaded.is_some() {
+            if cli.keep_state {
+                carried = machines;
+            }
+            continue;
+        }
+        current = (current + 1) % playlist.len();
     }
 
-    fn load_rom(&mut self, data: Vec<u8>) {
-        self.memory[0x200..(0x200 + data.len())].copy_from_slice(&data);
+    if cli.coverage || cli.coverage_addresses {
+        coverage.print(cli.coverage_addresses);
     }
-}
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let file_path = args.get(1).unwrap_or_else(|| {
-        eprintln!("Error: Usage - cargo run -- /path/to/rom");
-        exit(1);
-    });
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-    let audio_subsystem = sdl_context.audio().unwrap();
-
-    let window = video_subsystem
-        .window("rust-sdl2 demo", 640, 320)
-        .position_centered()
-        .build()
-        .unwrap();
-    let mut canvas = window.into_canvas().build().unwrap();
-
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
-    canvas.clear();
-
-    let desired_spec = AudioSpecDesired {
-        freq: Some(44100),
-        channels: Some(1), // mono
-        samples: None,     // default sample size
-    };
-
-    let device = audio_subsystem
-        .open_playback(None, &desired_spec, |spec| {
-            // initialize the audio callback
-            SquareWave {
-                phase_inc: 440.0 / spec.freq as f32,
-                phase: 0.0,
-                volume: 0.25,
-            }
-        })
-        .unwrap();
-    device.resume();
-    canvas.present();
-    let mut c8 = Chip8::new(canvas, device);
-
-    let mut data: Vec<u8> = Vec::new();
-    File::open(file_path)
-        .unwrap()
-        .read_to_end(&mut data)
-        .unwrap();
-
-    // this should wait for a keypress and then put a character on the screen
-    // let mut data: Vec<u8> = vec![
-    //    0xF1, 0x0A, // wait for key press
-    //    0x00, 0xE0, // clear the screen
-    //    0x61, 0x03, // set v1 to 05
-    //    0xF1, 0x29, // set i to location of character in v1
-    //    0x61, 0x38, // set v1 to 38
-    //    0x62, 0x00, // set v2 to 00
-    //    0xD1, 0x25, // draw at location in v1 and v2 for height of 5
-    //    0x00, 0x0F,
-    //    0x12, 0x0C, // jump to address 20c
-    // ];
-
-    c8.load_rom(data);
-    let mut event_pump = sdl_context.event_pump().unwrap();
-
-    'running: loop {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => break 'running,
-                Event::KeyDown {
-                    keycode: Some(key), ..
-                } => c8.handle_key_press(EventType::KeyDown, key),
-                Event::KeyUp {
-                    keycode: Some(key), ..
-                } => c8.handle_key_press(EventType::KeyUp, key),
-                _ => {}
-            }
+    if let (Some(telemetry), Some(path)) = (telemetry.as_mut(), &cli.telemetry) {
+        match telemetry.write(path) {
+            Ok(()) => info!("wrote telemetry to {}", path.display()),
+            Err(e) => error!("{}", e),
         }
-        let oc = c8.fetch();
-        let inst = c8.decode(oc);
-        c8.execute(inst);
-
-        c8.update_delay_timer();
-        c8.update_sound_timer();
-
-        // we need to run at about 60hz
-        Chip8::sleep();
     }
+    exit(status);
 }
@@ -0,0 +1,182 @@
+// Input movies: the keys held on every frame of a run, so it can be played
+// back headlessly. Recorded with `run --record-movie`, played back by
+// `bisect-quirks`. A text file:
+//
+//   chip8-rs movie 1
+//   rom <sha1>
+//   quirks <quirks>
+//   speed <instructions per frame>
+//   seed <seed>
+//   <frame> <keys>
+//   ...
+//
+// where each `<frame> <keys>` line says that from that frame on the keys in
+// the hex bitmask (bit n is keypad key n) are held, and the last line is the
+// frame the movie ends on. Macros (macros.rs) store their keys the same way,
+// as an `InputTrack`.
+use chip8_core::{Agent, Chip8, Quirks, RomId};
+use std::fs;
+use std::path::Path;
+
+const HEADER: &str = "chip8-rs movie 1";
+
+pub struct Movie {
+    pub rom: String,
+    pub quirks: Quirks,
+    pub speed: u32,
+    pub seed: u64,
+    track: InputTrack,
+}
+
+/// The keys held over a run of frames: `(frame, keys)` whenever the keys
+/// changed, in frame order, and the frame the run ends on.
+#[derive(Default)]
+pub struct InputTrack {
+    events: Vec<(u64, u16)>,
+    end: u64,
+}
+
+impl InputTrack {
+    /// Parse `<frame> <keys>` lines, the last of which is the end frame.
+    pub fn parse<'a>(lines: impl Iterator<Item = &'a str>) -> Result<InputTrack, String> {
+        let mut events: Vec<(u64, u16)> = Vec::new();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let event = line.split_once(' ').and_then(|(frame, keys)| {
+                Some((frame.parse().ok()?, u16::from_str_radix(keys, 16).ok()?))
+            });
+            match event {
+                Some((frame, _)) if events.last().is_some_and(|&(last, _)| frame <= last) => {
+                    return Err(format!("frame {} is out of order", frame))
+                }
+                Some(event) => events.push(event),
+                None => return Err(format!("bad line `{}`", line)),
+            }
+        }
+        let end = events.last().map_or(0, |&(frame, _)| frame);
+        Ok(InputTrack { events, end })
+    }
+
+    /// The `<frame> <keys>` lines for the track.
+    pub fn write(&self, text: &mut String) {
+        for (frame, keys) in &self.events {
+            text.push_str(&format!("{} {:04x}\n", frame, keys));
+        }
+        // mark the end even if the keys did not change on the last frame
+        if self
+            .events
+            .last()
+            .is_none_or(|&(frame, _)| frame < self.end)
+        {
+            text.push_str(&format!("{} {:04x}\n", self.end, self.keys_at(self.end)));
+        }
+    }
+
+    /// Note that `keys` are held on `frame`, which must not be before the
+    /// last frame noted, and end the track there or later.
+    pub fn hold(&mut self, frame: u64, keys: u16) {
+        debug_assert!(self.events.last().is_none_or(|&(last, _)| last <= frame));
+        if self.events.last().is_none_or(|&(_, last)| last != keys) {
+            self.events.push((frame, keys));
+        }
+        self.end = self.end.max(frame);
+    }
+
+    /// The keys held on `frame`.
+    pub fn keys_at(&self, frame: u64) -> u16 {
+        match self
+            .events
+            .binary_search_by_key(&frame, |&(frame, _)| frame)
+        {
+            Ok(i) => self.events[i].1,
+            Err(0) => 0,
+            Err(i) => self.events[i - 1].1,
+        }
+    }
+
+    /// The frame the track ends on.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    pub fn set_end(&mut self, frame: u64) {
+        self.end = frame;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+fn field<'a>(line: Option<&'a str>, name: &str) -> Result<&'a str, String> {
+    line.and_then(|line| line.strip_prefix(name))
+        .and_then(|rest| rest.strip_prefix(' '))
+        .ok_or_else(|| format!("expected `{} ...`", name))
+}
+
+impl Movie {
+    pub fn new(rom: &RomId, quirks: Quirks, speed: u32, seed: u64) -> Movie {
+        Movie {
+            rom: rom.sha1_hex(),
+            quirks,
+            speed,
+            seed,
+            track: InputTrack::default(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Movie, String> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("not a chip8-rs movie".to_string());
+        }
+        let rom = field(lines.next(), "rom")?.to_string();
+        let quirks = field(lines.next(), "quirks")?
+            .parse()
+            .map_err(|e| format!("{}", e))?;
+        let speed = field(lines.next(), "speed")?
+            .parse()
+            .map_err(|_| "bad speed".to_string())?;
+        let seed = field(lines.next(), "seed")?
+            .parse()
+            .map_err(|_| "bad seed".to_string())?;
+        let track = InputTrack::parse(lines)?;
+        Ok(Movie {
+            rom,
+            quirks,
+            speed,
+            seed,
+            track,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Movie, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Movie::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut text = format!(
+            "{}\nrom {}\nquirks {}\nspeed {}\nseed {}\n",
+            HEADER, self.rom, self.quirks, self.speed, self.seed
+        );
+        self.track.write(&mut text);
+        fs::write(path, text).map_err(|e| format!("cannot write {}: {}", path.display(), e))
+    }
+
+    /// Note the keys held on `c8` before running `frame`.
+    pub fn record(&mut self, frame: u64, c8: &Chip8) {
+        self.track.hold(frame, c8.keys());
+    }
+
+    /// The frame the movie ends on.
+    pub fn frames(&self) -> u64 {
+        self.track.end()
+    }
+
+    /// Set the keys the movie holds on `frame`, which must be the frame
+    /// `agent` is about to run.
+    pub fn apply(&self, agent: &mut Agent) {
+        agent.set_keys(self.track.keys_at(agent.frames()));
+    }
+}
@@ -0,0 +1,162 @@
+// Two player games over TCP (--host / --join). Both sides run the same ROM on
+// the same machine (quirks, font, memory and stack settings) at the same speed
+// and seed, and before every frame each sends the keys its player is holding
+// on their half of the keypad and waits for the other side's. The core is
+// deterministic, so with identical input both machines stay identical without
+// ever sending the screen.
+//
+// The host plays the left two columns of the keypad (1 2 4 5 7 8 A 0), the
+// guest the right two (3 C 6 D 9 E B F), which is how Pong and Tank split it.
+//
+// Protocol: each side first sends a hello, one text line that is wrapped here
+// to fit:
+//
+//   chip8-rs netplay 2 rom=<sha1> quirks=<quirks> speed=<n> font=<font>
+//     memory=<bytes> stack=<depth> memory-policy=<policy>
+//     write-protect=<mode> aligned-pc=<bool> seed=<n>
+//
+// Everything before the seed has to match, and the guest takes the host's
+// seed. After that every frame is 10 bytes each way: the held keys as a little
+// endian u16 bitmask, then the framebuffer hash before the frame, so a desync
+// can be reported.
+use chip8_core::{Chip8, Font, MemoryPolicy, Quirks, RomId, WriteProtection};
+use log::{info, warn};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+const VERSION: u32 = 2;
+
+// bit n is keypad key n
+const LEFT_HALF: u16 =
+    1 << 0x1 | 1 << 0x2 | 1 << 0x4 | 1 << 0x5 | 1 << 0x7 | 1 << 0x8 | 1 << 0xA | 1 << 0x0;
+
+/// What both sides have to agree on to stay in lockstep.
+pub struct Session {
+    pub rom: RomId,
+    pub quirks: Quirks,
+    pub speed: u32,
+    pub font: Font,
+    pub memory_size: usize,
+    pub stack_depth: usize,
+    pub memory_policy: MemoryPolicy,
+    pub write_protection: WriteProtection,
+    pub aligned_pc: bool,
+    pub seed: u64,
+}
+
+pub struct Netplay {
+    stream: TcpStream,
+    // the keys this side's player controls
+    mine: u16,
+    desynced: bool,
+}
+
+// the part of the hello line that has to match
+fn setup(session: &Session) -> String {
+    format!(
+        "chip8-rs netplay {} rom={} quirks={} speed={} font={} memory={} stack={} \
+         memory-policy={} write-protect={} aligned-pc={}",
+        VERSION,
+        session
+            .rom
+            .sha1
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>(),
+        session.quirks,
+        session.speed,
+        session.font,
+        session.memory_size,
+        session.stack_depth,
+        session.memory_policy,
+        session.write_protection,
+        session.aligned_pc
+    )
+}
+
+impl Netplay {
+    /// Wait for a guest on `addr`. Returns the seed both sides use, which is
+    /// the host's own.
+    pub fn host(addr: &str, session: &Session) -> Result<(Netplay, u64), String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+        info!("waiting for a player to join on {}", addr);
+        let (stream, peer) = listener
+            .accept()
+            .map_err(|e| format!("cannot accept a connection: {}", e))?;
+        info!("{} joined", peer);
+        let (mut netplay, _) = Netplay::handshake(stream, session)?;
+        netplay.mine = LEFT_HALF;
+        Ok((netplay, session.seed))
+    }
+
+    /// Connect to a host on `addr`. Returns the seed the host is using.
+    pub fn join(addr: &str, session: &Session) -> Result<(Netplay, u64), String> {
+        let stream =
+            TcpStream::connect(addr).map_err(|e| format!("cannot connect to {}: {}", addr, e))?;
+        info!("joined {}", addr);
+        let (mut netplay, seed) = Netplay::handshake(stream, session)?;
+        netplay.mine = !LEFT_HALF;
+        Ok((netplay, seed))
+    }
+
+    // swap hello lines and check the peer is running the same thing,
+    // returning the peer's seed
+    fn handshake(stream: TcpStream, session: &Session) -> Result<(Netplay, u64), String> {
+        let io_error = |e: std::io::Error| format!("netplay: {}", e);
+        stream.set_nodelay(true).map_err(io_error)?;
+        let hello = format!("{} seed={}\n", setup(session), session.seed);
+        (&stream).write_all(hello.as_bytes()).map_err(io_error)?;
+
+        // read a byte at a time so nothing after the line gets buffered away
+        let mut line = String::new();
+        BufReader::with_capacity(1, &stream)
+            .read_line(&mut line)
+            .map_err(io_error)?;
+        let (theirs, seed) = line
+            .trim_end()
+            .rsplit_once(" seed=")
+            .ok_or("netplay: the other side is not chip8-rs")?;
+        let ours = setup(session);
+        if ours != theirs {
+            return Err(format!(
+                "netplay: the other side runs a different setup\n  here:  {}\n  there: {}",
+                ours, theirs
+            ));
+        }
+        let seed = seed
+            .parse()
+            .map_err(|_| "netplay: bad seed from the other side".to_string())?;
+        let netplay = Netplay {
+            stream,
+            mine: 0,
+            desynced: false,
+        };
+        Ok((netplay, seed))
+    }
+
+    /// Swap inputs for the next frame and set the keypad of `c8` from both
+    /// players. Blocks until the other side has sent theirs.
+    pub fn exchange(&mut self, c8: &mut Chip8) -> Result<(), String> {
+        let local = c8.keys() & self.mine;
+        let hash = c8.framebuffer_hash();
+
+        let mut packet = [0; 10];
+        packet[..2].copy_from_slice(&local.to_le_bytes());
+        packet[2..].copy_from_slice(&hash.to_le_bytes());
+        let lost = |e: std::io::Error| format!("netplay: lost the other player: {}", e);
+        self.stream.write_all(&packet).map_err(lost)?;
+        self.stream.read_exact(&mut packet).map_err(lost)?;
+
+        let remote = u16::from_le_bytes([packet[0], packet[1]]) & !self.mine;
+        let mut their_hash = [0; 8];
+        their_hash.copy_from_slice(&packet[2..]);
+        if !self.desynced && u64::from_le_bytes(their_hash) != hash {
+            self.desynced = true;
+            warn!("netplay: the two screens no longer match");
+        }
+
+        c8.set_keys(local | remote);
+        Ok(())
+    }
+}
@@ -0,0 +1,782 @@
+// An assembler for the CHIP-8 subset of Octo (https://github.com/JohnEarnest/Octo),
+// so .o8 sources can be run without a separate build step.
+//
+// Execution starts at the `main` label, through a jump placed at 0x200.
+// Supported:
+//
+//   : name  :next name  :const name value  :alias name vX  :org addr
+//   :unpack nibble label  :byte value  :call label  :breakpoint name
+//   clear  return ;  jump addr  jump0 addr  label (a call)  bcd  save  load
+//   sprite vX vY n  delay := vX  buzzer := vX  i := addr  i := hex vX  i += vX
+//   vX := n / vY / random n / delay / key   vX += -= n / vY   vX =- |= &= ^=
+//   >>= <<= vY   if ... then  if ... begin ... else ... end
+//   loop ... while ... again   bare numbers and constants as data bytes
+//
+// with the conditions ==, != (against a number or a register), <, >, <=, >=
+// (which use vf as scratch, like Octo) and key / -key.
+//
+// :macro, :calc, :stringmode and the SCHIP / XO-CHIP instructions are not
+// supported and stop with an error.
+use std::collections::{BTreeMap, HashMap};
+
+const START: usize = 0x200;
+
+/// An assembled program.
+pub struct Program {
+    pub rom: Vec<u8>,
+    pub symbols: Symbols,
+}
+
+/// The labels of an assembled program, so the console and crash reports can
+/// name addresses the way the source does.
+#[derive(Clone, Default)]
+pub struct Symbols {
+    labels: BTreeMap<String, usize>,
+    // the first label defined at each address
+    by_addr: BTreeMap<usize, String>,
+}
+
+impl Symbols {
+    fn new(labels: BTreeMap<String, usize>) -> Symbols {
+        let mut by_addr = BTreeMap::new();
+        for (name, &addr) in &labels {
+            by_addr.entry(addr).or_insert_with(|| name.clone());
+        }
+        Symbols { labels, by_addr }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.labels.get(name).copied()
+    }
+
+    /// `addr` relative to the closest label at or before it, like `main+4`.
+    pub fn describe(&self, addr: usize) -> Option<String> {
+        let (&label_addr, name) = self.by_addr.range(..=addr).next_back()?;
+        Some(match addr - label_addr {
+            0 => name.clone(),
+            offset => format!("{}+{}", name, offset),
+        })
+    }
+
+    /// Every label, by address.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        let mut labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|(name, &addr)| (addr, name.as_str()))
+            .collect();
+        labels.sort();
+        labels.into_iter()
+    }
+}
+
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+enum Value {
+    Known(i64),
+    // a label that has not been defined yet
+    Forward(String),
+}
+
+enum Fixup {
+    // the low 12 bits of the instruction at the address
+    Addr,
+    // the two instructions of an :unpack, with the nibble to put above the
+    // address
+    Unpack(u8),
+}
+
+enum Block {
+    If { jump: usize },
+    Else { jump: usize },
+    Loop { start: usize, breaks: Vec<usize> },
+}
+
+// a condition as the skips that implement it: `prefix` computes vf for the
+// comparisons, then `when_false` skips the next instruction unless the
+// condition holds and `when_true` skips it if it does
+struct Condition {
+    prefix: Vec<u16>,
+    when_false: u16,
+    when_true: u16,
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    rom: Vec<u8>,
+    here: usize,
+    labels: BTreeMap<String, usize>,
+    consts: HashMap<String, i64>,
+    aliases: HashMap<String, usize>,
+    fixups: Vec<(usize, String, Fixup, usize)>,
+    blocks: Vec<(Block, usize)>,
+}
+
+/// Assemble the Octo source `text` into a ROM loaded at 0x200. Errors name the
+/// line they were found on.
+pub fn assemble(text: &str) -> Result<Program, String> {
+    let tokens = text
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            code.split_whitespace()
+                .map(move |text| Token { text, line: i + 1 })
+        })
+        .collect();
+    let mut asm = Assembler {
+        tokens,
+        pos: 0,
+        rom: Vec::new(),
+        here: START,
+        labels: BTreeMap::new(),
+        consts: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+    };
+    // jump main, patched once everything is defined
+    asm.emit_addr(0x1000, Value::Forward("main".to_string()), 0)?;
+    while asm.pos < asm.tokens.len() {
+        let line = asm.tokens[asm.pos].line;
+        asm.statement()
+            .map_err(|e| format!("line {}: {}", line, e))?;
+    }
+    asm.finish()
+}
+
+fn parse_number(s: &str) -> Option<i64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+// {:#x} would show a negative address as its two's complement
+fn out_of_range(addr: i64) -> String {
+    let sign = if addr < 0 { "-" } else { "" };
+    format!("address {}{:#x} is out of range", sign, addr.unsigned_abs())
+}
+
+fn register(name: &str) -> Option<usize> {
+    let digit = name.strip_prefix('v').or_else(|| name.strip_prefix('V'))?;
+    match usize::from_str_radix(digit, 16) {
+        Ok(reg) if digit.len() == 1 => Some(reg),
+        _ => None,
+    }
+}
+
+fn is_unsupported(word: &str) -> bool {
+    matches!(
+        word,
+        ":macro"
+            | ":calc"
+            | ":stringmode"
+            | ":assert"
+            | ":pointer"
+            | "hires"
+            | "lores"
+            | "scroll-down"
+            | "scroll-up"
+            | "scroll-left"
+            | "scroll-right"
+            | "exit"
+            | "saveflags"
+            | "loadflags"
+            | "plane"
+            | "audio"
+            | "pitch"
+            | "bighex"
+            | "long"
+    )
+}
+
+impl<'a> Assembler<'a> {
+    fn next(&mut self) -> Result<&'a str, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| "unexpected end of file".to_string())?;
+        self.pos += 1;
+        Ok(token.text)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|token| token.text)
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), String> {
+        match self.next()? {
+            found if found == word => Ok(()),
+            found => Err(format!("expected `{}`, found `{}`", word, found)),
+        }
+    }
+
+    fn put(&mut self, addr: usize, byte: u8) {
+        let offset = addr - START;
+        if offset >= self.rom.len() {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        self.put(self.here, byte);
+        self.here += 1;
+    }
+
+    fn emit(&mut self, word: u16) {
+        self.emit_byte((word >> 8) as u8);
+        self.emit_byte(word as u8);
+    }
+
+    // point the instruction at `addr` at `target`
+    fn patch(&mut self, addr: usize, target: usize) -> Result<(), String> {
+        if target > 0xFFF {
+            return Err(format!("address {:#x} is out of range", target));
+        }
+        let high = self.rom[addr - START] & 0xF0;
+        self.put(addr, high | (target >> 8) as u8);
+        self.put(addr + 1, target as u8);
+        Ok(())
+    }
+
+    fn emit_addr(&mut self, op: u16, target: Value, line: usize) -> Result<(), String> {
+        match target {
+            Value::Known(addr) if (0..=0xFFF).contains(&addr) => self.emit(op | addr as u16),
+            Value::Known(addr) => return Err(out_of_range(addr)),
+            Value::Forward(name) => {
+                self.fixups.push((self.here, name, Fixup::Addr, line));
+                self.emit(op);
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        let word = self.next()?;
+        if let Some(n) = parse_number(word) {
+            return Ok(Value::Known(n));
+        }
+        if let Some(&n) = self.consts.get(word) {
+            return Ok(Value::Known(n));
+        }
+        if let Some(&addr) = self.labels.get(word) {
+            return Ok(Value::Known(addr as i64));
+        }
+        if register(word).is_some() || self.aliases.contains_key(word) {
+            return Err(format!("expected a value, found register `{}`", word));
+        }
+        Ok(Value::Forward(word.to_string()))
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        match self.value()? {
+            Value::Known(n) if (-128..=255).contains(&n) => Ok(n as u8),
+            Value::Known(n) => Err(format!("{} does not fit in a byte", n)),
+            Value::Forward(name) => Err(format!("`{}` is not defined", name)),
+        }
+    }
+
+    fn nibble(&mut self) -> Result<u8, String> {
+        match self.byte()? {
+            n if n < 16 => Ok(n),
+            n => Err(format!("{} does not fit in 4 bits", n)),
+        }
+    }
+
+    fn reg_name(&self, word: &str) -> Option<usize> {
+        register(word).or_else(|| self.aliases.get(word).copied())
+    }
+
+    fn reg(&mut self) -> Result<usize, String> {
+        let word = self.next()?;
+        self.reg_name(word)
+            .ok_or_else(|| format!("expected a register, found `{}`", word))
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let word = self.next()?;
+        if parse_number(word).is_some() || self.reg_name(word).is_some() {
+            return Err(format!("`{}` cannot be used as a name", word));
+        }
+        Ok(word.to_string())
+    }
+
+    fn define_label(&mut self, name: String, addr: usize) -> Result<(), String> {
+        if self.labels.insert(name.clone(), addr).is_some() {
+            return Err(format!("label `{}` is defined twice", name));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let line = self.tokens[self.pos].line;
+        let word = self.next()?;
+        if is_unsupported(word) {
+            return Err(format!("`{}` is not supported", word));
+        }
+        if let Some(x) = self.reg_name(word) {
+            return self.register_statement(x);
+        }
+        match word {
+            ":" => {
+                let name = self.name()?;
+                self.define_label(name, self.here)?;
+            }
+            ":next" => {
+                let name = self.name()?;
+                self.define_label(name, self.here + 1)?;
+            }
+            ":const" => {
+                let name = self.name()?;
+                match self.value()? {
+                    Value::Known(n) => {
+                        self.consts.insert(name, n);
+                    }
+                    Value::Forward(other) => return Err(format!("`{}` is not defined", other)),
+                }
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let reg = self.reg()?;
+                self.aliases.insert(name, reg);
+            }
+            ":org" => match self.value()? {
+                Value::Known(addr) if (START as i64..=0xFFF).contains(&addr) => {
+                    self.here = addr as usize;
+                }
+                Value::Known(addr) => return Err(format!(":org {:#x} is out of range", addr)),
+                Value::Forward(name) => return Err(format!("`{}` is not defined", name)),
+            },
+            ":unpack" => {
+                let nibble = self.nibble()?;
+                match self.value()? {
+                    Value::Known(addr) if (0..=0xFFF).contains(&addr) => {
+                        self.emit(0x6000 | (nibble as u16) << 4 | addr as u16 >> 8);
+                        self.emit(0x6100 | (addr as u16 & 0xFF));
+                    }
+                    Value::Known(addr) => return Err(out_of_range(addr)),
+                    Value::Forward(name) => {
+                        self.fixups
+                            .push((self.here, name, Fixup::Unpack(nibble), line));
+                        self.emit(0x6000);
+                        self.emit(0x6100);
+                    }
+                }
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_byte(byte);
+            }
+            ":call" => {
+                let target = self.value()?;
+                self.emit_addr(0x2000, target, line)?;
+            }
+            ":breakpoint" => {
+                self.name()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "jump" => {
+                let target = self.value()?;
+                self.emit_addr(0x1000, target, line)?;
+            }
+            "jump0" => {
+                let target = self.value()?;
+                self.emit_addr(0xB000, target, line)?;
+            }
+            "bcd" | "save" | "load" => {
+                let x = self.reg()? as u16;
+                if self.peek() == Some("-") {
+                    return Err(format!("`{} vx - vy` is not supported", word));
+                }
+                let op = match word {
+                    "bcd" => 0xF033,
+                    "save" => 0xF055,
+                    _ => 0xF065,
+                };
+                self.emit(op | x << 8);
+            }
+            "sprite" => {
+                let x = self.reg()? as u16;
+                let y = self.reg()? as u16;
+                let n = self.nibble()? as u16;
+                self.emit(0xD000 | x << 8 | y << 4 | n);
+            }
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let x = self.reg()? as u16;
+                self.emit(if word == "delay" { 0xF015 } else { 0xF018 } | x << 8);
+            }
+            "i" => self.index_statement(line)?,
+            "if" => {
+                let condition = self.condition()?;
+                match self.next()? {
+                    "then" => {
+                        condition.prefix.iter().for_each(|&op| self.emit(op));
+                        self.emit(condition.when_false);
+                    }
+                    "begin" => {
+                        condition.prefix.iter().for_each(|&op| self.emit(op));
+                        self.emit(condition.when_true);
+                        self.blocks.push((Block::If { jump: self.here }, line));
+                        self.emit(0x1000);
+                    }
+                    other => return Err(format!("expected `then` or `begin`, found `{}`", other)),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some((Block::If { jump }, _)) => {
+                    let end_jump = self.here;
+                    self.emit(0x1000);
+                    self.patch(jump, self.here)?;
+                    self.blocks.push((Block::Else { jump: end_jump }, line));
+                }
+                _ => return Err("`else` without `if ... begin`".to_string()),
+            },
+            "end" => match self.blocks.pop() {
+                Some((Block::If { jump }, _)) | Some((Block::Else { jump }, _)) => {
+                    self.patch(jump, self.here)?;
+                }
+                _ => return Err("`end` without `if ... begin`".to_string()),
+            },
+            "loop" => self.blocks.push((
+                Block::Loop {
+                    start: self.here,
+                    breaks: Vec::new(),
+                },
+                line,
+            )),
+            "while" => {
+                let condition = self.condition()?;
+                condition.prefix.iter().for_each(|&op| self.emit(op));
+                self.emit(condition.when_true);
+                let jump = self.here;
+                self.emit(0x1000);
+                let innermost = self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find_map(|(block, _)| match block {
+                        Block::Loop { breaks, .. } => Some(breaks),
+                        _ => None,
+                    });
+                innermost
+                    .ok_or_else(|| "`while` outside of a loop".to_string())?
+                    .push(jump);
+            }
+            "again" => match self.blocks.pop() {
+                Some((Block::Loop { start, breaks }, _)) => {
+                    self.emit_addr(0x1000, Value::Known(start as i64), line)?;
+                    for jump in breaks {
+                        self.patch(jump, self.here)?;
+                    }
+                }
+                _ => return Err("`again` without `loop`".to_string()),
+            },
+            _ => {
+                // a number or constant is a data byte, anything else calls a
+                // label
+                self.pos -= 1;
+                match self.value()? {
+                    Value::Known(n)
+                        if parse_number(word).is_some() || self.consts.contains_key(word) =>
+                    {
+                        if !(-128..=255).contains(&n) {
+                            return Err(format!("{} does not fit in a byte", n));
+                        }
+                        self.emit_byte(n as u8);
+                    }
+                    target => self.emit_addr(0x2000, target, line)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn index_statement(&mut self, line: usize) -> Result<(), String> {
+        match self.next()? {
+            ":=" => {
+                if let Some(word @ ("bighex" | "long")) = self.peek() {
+                    return Err(format!("`i := {}` is not supported", word));
+                }
+                if self.peek() == Some("hex") {
+                    self.next()?;
+                    let x = self.reg()? as u16;
+                    self.emit(0xF029 | x << 8);
+                } else {
+                    let target = self.value()?;
+                    self.emit_addr(0xA000, target, line)?;
+                }
+            }
+            "+=" => {
+                let x = self.reg()? as u16;
+                self.emit(0xF01E | x << 8);
+            }
+            other => return Err(format!("unknown operator `i {}`", other)),
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, x: usize) -> Result<(), String> {
+        let x = x as u16;
+        let op = self.next()?;
+        let rhs = self.peek().unwrap_or("");
+        if let Some(y) = self.reg_name(rhs) {
+            self.next()?;
+            let y = y as u16;
+            let n = match op {
+                ":=" => 0x0,
+                "|=" => 0x1,
+                "&=" => 0x2,
+                "^=" => 0x3,
+                "+=" => 0x4,
+                "-=" => 0x5,
+                ">>=" => 0x6,
+                "=-" => 0x7,
+                "<<=" => 0xE,
+                other => return Err(format!("unknown operator `{}`", other)),
+            };
+            self.emit(0x8000 | x << 8 | y << 4 | n);
+            return Ok(());
+        }
+        match (op, rhs) {
+            (":=", "random") => {
+                self.next()?;
+                let mask = self.byte()? as u16;
+                self.emit(0xC000 | x << 8 | mask);
+            }
+            (":=", "delay") => {
+                self.next()?;
+                self.emit(0xF007 | x << 8);
+            }
+            (":=", "key") => {
+                self.next()?;
+                self.emit(0xF00A | x << 8);
+            }
+            (":=", _) => {
+                let n = self.byte()? as u16;
+                self.emit(0x6000 | x << 8 | n);
+            }
+            ("+=", _) => {
+                let n = self.byte()? as u16;
+                self.emit(0x7000 | x << 8 | n);
+            }
+            ("-=", _) => {
+                let n = self.byte()?.wrapping_neg() as u16;
+                self.emit(0x7000 | x << 8 | n);
+            }
+            (other, _) => return Err(format!("unknown operator `{}` with a number", other)),
+        }
+        Ok(())
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let x = self.reg()? as u16;
+        let op = self.next()?;
+        let skip = |when_false: u16, when_true: u16| Condition {
+            prefix: Vec::new(),
+            when_false,
+            when_true,
+        };
+        match op {
+            "key" => return Ok(skip(0xE0A1 | x << 8, 0xE09E | x << 8)),
+            "-key" => return Ok(skip(0xE09E | x << 8, 0xE0A1 | x << 8)),
+            _ => {}
+        }
+
+        let rhs = self.peek().unwrap_or("");
+        let y = self.reg_name(rhs).map(|y| y as u16);
+        let n = match y {
+            Some(_) => {
+                self.next()?;
+                0
+            }
+            None => self.byte()? as u16,
+        };
+        let condition = match (op, y) {
+            ("==", Some(y)) => skip(0x9000 | x << 8 | y << 4, 0x5000 | x << 8 | y << 4),
+            ("!=", Some(y)) => skip(0x5000 | x << 8 | y << 4, 0x9000 | x << 8 | y << 4),
+            ("==", None) => skip(0x4000 | x << 8 | n, 0x3000 | x << 8 | n),
+            ("!=", None) => skip(0x3000 | x << 8 | n, 0x4000 | x << 8 | n),
+            (">=" | "<" | "<=" | ">", _) => {
+                // vf ends up as the no-borrow flag of a subtraction
+                let prefix = match (op, y) {
+                    // vf = x >= rhs
+                    (">=" | "<", Some(y)) => vec![0x8F00 | x << 4, 0x8F05 | y << 4],
+                    (">=" | "<", None) => vec![0x6F00 | n, 0x8F07 | x << 4],
+                    // vf = rhs >= x
+                    (_, Some(y)) => vec![0x8F00 | y << 4, 0x8F05 | x << 4],
+                    (_, None) => vec![0x6F00 | n, 0x8F05 | x << 4],
+                };
+                let holds = if op == ">=" || op == "<=" { 1 } else { 0 };
+                Condition {
+                    prefix,
+                    when_false: 0x4F00 | holds,
+                    when_true: 0x3F00 | holds,
+                }
+            }
+            (other, _) => return Err(format!("unknown comparison `{}`", other)),
+        };
+        Ok(condition)
+    }
+
+    fn finish(mut self) -> Result<Program, String> {
+        if let Some((_, line)) = self.blocks.last() {
+            return Err(format!("line {}: block is never closed", line));
+        }
+        for (addr, name, fixup, line) in std::mem::take(&mut self.fixups) {
+            let target = *self.labels.get(&name).ok_or_else(|| match line {
+                0 => "the program has no `main` label".to_string(),
+                _ => format!("line {}: `{}` is not defined", line, name),
+            })?;
+            match fixup {
+                Fixup::Addr => self
+                    .patch(addr, target)
+                    .map_err(|e| format!("line {}: {}", line, e))?,
+                Fixup::Unpack(_) if target > 0xFFF => {
+                    return Err(format!(
+                        "line {}: address {:#x} is out of range",
+                        line, target
+                    ))
+                }
+                Fixup::Unpack(nibble) => {
+                    self.put(addr + 1, nibble << 4 | (target >> 8) as u8);
+                    self.put(addr + 3, target as u8);
+                }
+            }
+        }
+        Ok(Program {
+            rom: self.rom,
+            symbols: Symbols::new(self.labels),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the program after the jump to main at 0x200
+    fn body(text: &str) -> Vec<u8> {
+        assemble(text).unwrap().rom[2..].to_vec()
+    }
+
+    #[test]
+    fn starts_with_a_jump_to_main() {
+        let program = assemble(": helper return\n: main clear").unwrap();
+        assert_eq!(program.rom, [0x12, 0x04, 0x00, 0xEE, 0x00, 0xE0]);
+        assert_eq!(program.symbols.get("main"), Some(0x204));
+        assert_eq!(program.symbols.describe(0x204).as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn assembles_register_statements() {
+        assert_eq!(
+            body(": main v0 := 0x2A v1 += 3 v2 -= 1 v3 := v4 v5 ^= v6 va := random 0x0F"),
+            [0x60, 0x2A, 0x71, 0x03, 0x72, 0xFF, 0x83, 0x40, 0x85, 0x63, 0xCA, 0x0F]
+        );
+        assert_eq!(
+            body(": main i := 0x300 i += v1 i := hex v2 sprite v0 v1 5 bcd v3 save v4"),
+            [0xA3, 0x00, 0xF1, 0x1E, 0xF2, 0x29, 0xD0, 0x15, 0xF3, 0x33, 0xF4, 0x55]
+        );
+    }
+
+    #[test]
+    fn resolves_forward_labels_and_constants() {
+        let text = ":const speed 4\n: main v0 := speed i := data jump main\n: data 0xFF";
+        assert_eq!(body(text), [0x60, 0x04, 0xA2, 0x08, 0x12, 0x02, 0xFF]);
+        assert_eq!(
+            body(": main :unpack 0xA data\n: data 1"),
+            [0x60, 0xA2, 0x61, 0x06, 0x01]
+        );
+    }
+
+    #[test]
+    fn assembles_blocks() {
+        // the skip steps over the block's exit jump while the condition holds
+        assert_eq!(
+            body(": main if v0 == 1 begin v1 := 1 else v1 := 2 end"),
+            [0x30, 0x01, 0x12, 0x0A, 0x61, 0x01, 0x12, 0x0C, 0x61, 0x02]
+        );
+        assert_eq!(
+            body(": main loop v0 += 1 while v0 != 9 again"),
+            [0x70, 0x01, 0x40, 0x09, 0x12, 0x0A, 0x12, 0x02]
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_their_line() {
+        let error = |text| assemble(text).err().unwrap();
+        assert_eq!(
+            error(": main\n  jump nowhere"),
+            "line 2: `nowhere` is not defined"
+        );
+        assert_eq!(
+            error(": main\n  v0 := 256"),
+            "line 2: 256 does not fit in a byte"
+        );
+        assert_eq!(error("clear"), "the program has no `main` label");
+        assert_eq!(
+            error(": main\n  if v0 == 1 begin"),
+            "line 2: block is never closed"
+        );
+        assert_eq!(
+            error(": main\n  :macro"),
+            "line 2: `:macro` is not supported"
+        );
+    }
+
+    #[test]
+    fn addresses_past_0xfff_are_errors() {
+        let error = |text| assemble(text).err().unwrap();
+        assert_eq!(
+            error(": main jump later\n:org 0xFFF 0 : later"),
+            "line 1: address 0x1000 is out of range"
+        );
+        assert_eq!(
+            error(": main :org 0xFFE if v0 == 1 begin clear end"),
+            "line 1: address 0x1004 is out of range"
+        );
+    }
+
+    #[test]
+    fn unpacked_addresses_past_0xfff_are_errors() {
+        let error = |text| assemble(text).err().unwrap();
+        assert_eq!(
+            error(": main\n  :unpack 0 0x1234"),
+            "line 2: address 0x1234 is out of range"
+        );
+        assert_eq!(
+            error(": main\n  :unpack 0 -1"),
+            "line 2: address -0x1 is out of range"
+        );
+        assert_eq!(
+            error(": main :unpack 0 later\n:org 0xFFF 0 : later"),
+            "line 1: address 0x1000 is out of range"
+        );
+    }
+}
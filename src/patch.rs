@@ -0,0 +1,319 @@
+// IPS and BPS patches for --patch, applied to the ROM bytes before they are
+// loaded so fixes and translations can be shared without the ROM itself.
+//
+// IPS: "PATCH", then records of a 24 bit big endian offset and a 16 bit size
+// followed by that many bytes, or a size of 0 followed by a 16 bit count and
+// one byte to repeat, up to "EOF". A 24 bit length to truncate the result to
+// may follow.
+//
+// BPS: "BPS1", then the source size, target size and metadata size as
+// variable length numbers, the metadata, and actions up to the last 12 bytes,
+// which hold the CRC32 of the source, the target and the patch so far, little
+// endian. A patch for a different ROM is refused.
+use chip8_core::{RomId, MAX_MEMORY_SIZE};
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos.saturating_add(n))
+            .ok_or("the patch is truncated")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn be(&mut self, n: usize) -> Result<usize, String> {
+        Ok(self
+            .take(n)?
+            .iter()
+            .fold(0, |acc, &b| (acc << 8) | b as usize))
+    }
+
+    // BPS numbers: 7 bits at a time, the high bit set on the last byte
+    fn number(&mut self) -> Result<usize, String> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.u8()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|v| v.checked_add(value))
+                .ok_or("the patch is corrupt")?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or("the patch is corrupt")?;
+            value = value.checked_add(shift).ok_or("the patch is corrupt")?;
+        }
+    }
+
+    // a signed offset for the BPS copy actions, sign in the low bit
+    fn offset(&mut self, from: usize) -> Result<usize, String> {
+        let n = self.number()?;
+        let moved = if n & 1 == 0 {
+            from.checked_add(n >> 1)
+        } else {
+            from.checked_sub(n >> 1)
+        };
+        moved.ok_or_else(|| "the patch is corrupt".to_string())
+    }
+}
+
+fn ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = rom.to_vec();
+    let mut reader = Reader {
+        data: patch,
+        pos: 5,
+    };
+    loop {
+        if reader.data.get(reader.pos..reader.pos + 3) == Some(b"EOF") {
+            reader.pos += 3;
+            break;
+        }
+        let offset = reader.be(3)?;
+        let (bytes, len) = match reader.be(2)? {
+            0 => {
+                let len = reader.be(2)?;
+                (vec![reader.u8()?; len], len)
+            }
+            len => (reader.take(len)?.to_vec(), len),
+        };
+        if out.len() < offset + len {
+            out.resize(offset + len, 0);
+        }
+        out[offset..offset + len].copy_from_slice(&bytes);
+    }
+    if reader.pos + 3 <= patch.len() {
+        out.truncate(reader.be(3)?);
+    }
+    Ok(out)
+}
+
+fn bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.len() < 4 + 12 {
+        return Err("the patch is truncated".to_string());
+    }
+    let crc = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+    let footer = &patch[patch.len() - 12..];
+    if RomId::of(&patch[..patch.len() - 4]).crc32 != crc(&footer[8..]) {
+        return Err("the patch is corrupt".to_string());
+    }
+    if RomId::of(rom).crc32 != crc(&footer[..4]) {
+        return Err("the patch is for a different ROM".to_string());
+    }
+
+    let mut reader = Reader {
+        data: &patch[..patch.len() - 12],
+        pos: 4,
+    };
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.take(metadata_size)?;
+    if source_size != rom.len() {
+        return Err("the patch is for a different ROM".to_string());
+    }
+    if target_size > MAX_MEMORY_SIZE {
+        return Err(format!(
+            "the patch makes a {} byte ROM, more than fits in memory",
+            target_size
+        ));
+    }
+
+    // the sizes come from the patch, so nothing is trusted before the checksum
+    // of the result is
+    let mut out: Vec<u8> = Vec::with_capacity(target_size);
+    let (mut source_offset, mut target_offset) = (0, 0);
+    while reader.pos < reader.data.len() {
+        let action = reader.number()?;
+        let len = (action >> 2) + 1;
+        if len > target_size - out.len() {
+            return Err("the patch writes past the end of its target".to_string());
+        }
+        let past_end = || "the patch reads past the end of the ROM".to_string();
+        match action & 3 {
+            // source read, the bytes at the same position in the ROM
+            0 => {
+                let at = out.len();
+                out.extend_from_slice(rom.get(at..at.saturating_add(len)).ok_or_else(past_end)?);
+            }
+            // target read, bytes from the patch
+            1 => out.extend_from_slice(reader.take(len)?),
+            // source copy, from anywhere in the ROM
+            2 => {
+                source_offset = reader.offset(source_offset)?;
+                let bytes = rom
+                    .get(source_offset..source_offset.saturating_add(len))
+                    .ok_or_else(past_end)?;
+                out.extend_from_slice(bytes);
+                source_offset += len;
+            }
+            // target copy, from the output so far, byte by byte since the
+            // ranges may overlap
+            _ => {
+                target_offset = reader.offset(target_offset)?;
+                for _ in 0..len {
+                    let byte = *out.get(target_offset).ok_or("the patch is corrupt")?;
+                    out.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if out.len() != target_size || RomId::of(&out).crc32 != crc(&footer[4..8]) {
+        return Err("the patched ROM does not match the patch's checksum".to_string());
+    }
+    Ok(out)
+}
+
+/// Apply an IPS or BPS `patch` to `rom`, telling them apart by their magic.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.starts_with(b"PATCH") {
+        ips(rom, patch)
+    } else if patch.starts_with(b"BPS1") {
+        bps(rom, patch)
+    } else {
+        Err("not an IPS or BPS patch".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ips_patch(records: &[u8], truncate: Option<usize>) -> Vec<u8> {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(records);
+        patch.extend_from_slice(b"EOF");
+        if let Some(len) = truncate {
+            patch.extend_from_slice(&(len as u32).to_be_bytes()[1..]);
+        }
+        patch
+    }
+
+    fn number(out: &mut Vec<u8>, mut n: usize) {
+        loop {
+            let low = (n & 0x7F) as u8;
+            n >>= 7;
+            if n == 0 {
+                out.push(0x80 | low);
+                return;
+            }
+            out.push(low);
+            n -= 1;
+        }
+    }
+
+    fn bps_patch(source: &[u8], target: &[u8], target_size: usize, actions: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        number(&mut patch, source.len());
+        number(&mut patch, target_size);
+        number(&mut patch, 0);
+        patch.extend_from_slice(actions);
+        patch.extend_from_slice(&RomId::of(source).crc32.to_le_bytes());
+        patch.extend_from_slice(&RomId::of(target).crc32.to_le_bytes());
+        let crc = RomId::of(&patch).crc32;
+        patch.extend_from_slice(&crc.to_le_bytes());
+        patch
+    }
+
+    // an action number: the length less one above the two bit kind
+    fn action(kind: usize, len: usize) -> usize {
+        ((len - 1) << 2) | kind
+    }
+
+    #[test]
+    fn ips_records_and_rle() {
+        let records = [
+            0, 0, 1, 0, 2, 0xAA, 0xBB, // 2 bytes at 1
+            0, 0, 4, 0, 0, 0, 3, 0xCC, // 3 x 0xCC at 4, past the end
+        ];
+        let patched = apply(&[1, 2, 3, 4], &ips_patch(&records, None)).unwrap();
+        assert_eq!(patched, [1, 0xAA, 0xBB, 4, 0xCC, 0xCC, 0xCC]);
+    }
+
+    #[test]
+    fn ips_truncates() {
+        let records = [0, 0, 0, 0, 1, 9];
+        let patched = apply(&[1, 2, 3, 4], &ips_patch(&records, Some(2))).unwrap();
+        assert_eq!(patched, [9, 2]);
+    }
+
+    #[test]
+    fn ips_truncated_record_is_an_error() {
+        let patch = b"PATCH\0\0\x01\0\x05\xAA".to_vec();
+        assert!(apply(&[1, 2, 3], &patch).is_err());
+    }
+
+    #[test]
+    fn bps_source_and_target_reads() {
+        let source = [1, 2, 3, 4];
+        let target = [1, 2, 9, 4];
+        let mut actions = Vec::new();
+        number(&mut actions, action(0, 2));
+        number(&mut actions, action(1, 1));
+        actions.push(9);
+        // source read takes the byte at the output position, 3 here
+        number(&mut actions, action(2, 1));
+        number(&mut actions, 3 << 1);
+        let patch = bps_patch(&source, &target, 4, &actions);
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn bps_source_and_target_copies() {
+        let source = [1, 2, 3, 4];
+        // 4 3 from the end of the source backwards one at a time, then the
+        // output so far repeated with an overlapping target copy
+        let target = [4, 3, 4, 3, 4, 3];
+        let mut actions = Vec::new();
+        number(&mut actions, action(2, 1));
+        number(&mut actions, 3 << 1);
+        number(&mut actions, action(2, 1));
+        number(&mut actions, (2 << 1) | 1); // back from 4 to 2
+        number(&mut actions, action(3, 4));
+        number(&mut actions, 0);
+        let patch = bps_patch(&source, &target, 6, &actions);
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn bps_for_another_rom_is_refused() {
+        let patch = bps_patch(&[1, 2, 3], &[1], 1, &[0x80]);
+        assert_eq!(
+            apply(&[1, 2, 4], &patch),
+            Err("the patch is for a different ROM".to_string())
+        );
+    }
+
+    #[test]
+    fn bps_sizes_are_not_trusted() {
+        let source = [1, 2, 3, 4];
+        // a target far too big to allocate up front
+        let patch = bps_patch(&source, &[], usize::MAX >> 8, &[]);
+        assert!(apply(&source, &patch)
+            .unwrap_err()
+            .ends_with("byte ROM, more than fits in memory"));
+
+        // a target copy that would repeat one byte forever
+        let mut actions = Vec::new();
+        number(&mut actions, action(1, 1));
+        actions.push(7);
+        number(&mut actions, action(3, 1 << 40));
+        number(&mut actions, 0);
+        let patch = bps_patch(&source, &[7; 4], 4, &actions);
+        assert_eq!(
+            apply(&source, &patch),
+            Err("the patch writes past the end of its target".to_string())
+        );
+    }
+}
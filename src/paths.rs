@@ -0,0 +1,88 @@
+// Where files live, following the platform conventions through the
+// `directories` crate: on Linux the config file in ~/.config/chip8-rs, save
+// states, cheats and the recent ROM list in ~/.local/share/chip8-rs and screenshots in
+// ~/Pictures/chip8-rs. Directories are created the first time they are asked
+// for, and `None` means there is nowhere sensible to put the file.
+use crate::rom;
+use directories::{ProjectDirs, UserDirs};
+use log::warn;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn project() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "chip8-rs")
+}
+
+fn create(dir: PathBuf) -> Option<PathBuf> {
+    match fs::create_dir_all(&dir) {
+        Ok(()) => Some(dir),
+        Err(e) => {
+            warn!("cannot create {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+// the name files for a ROM are stored under
+fn stem(rom: &Path) -> String {
+    if rom::is_stdin(rom) {
+        return "stdin".to_string();
+    }
+    rom.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "rom".to_string())
+}
+
+pub fn config_file() -> Option<PathBuf> {
+    Some(create(project()?.config_dir().to_path_buf())?.join("config.toml"))
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    create(project()?.data_dir().to_path_buf())
+}
+
+/// The save state slot for `rom`.
+pub fn state_file(rom: &Path) -> Option<PathBuf> {
+    let dir = create(data_dir()?.join("states"))?;
+    Some(dir.join(stem(rom) + ".state"))
+}
+
+/// Where the cheats for `rom` are looked for when --cheats is not given.
+pub fn cheat_file(rom: &Path) -> Option<PathBuf> {
+    let dir = create(data_dir()?.join("cheats"))?;
+    Some(dir.join(stem(rom) + ".cht"))
+}
+
+/// Where the persisted memory range of `rom` is kept.
+pub fn persist_file(rom: &Path) -> Option<PathBuf> {
+    let dir = create(data_dir()?.join("persist"))?;
+    Some(dir.join(stem(rom) + ".bin"))
+}
+
+/// The keypad macro recorded for `rom`.
+pub fn macro_file(rom: &Path) -> Option<PathBuf> {
+    let dir = create(data_dir()?.join("macros"))?;
+    Some(dir.join(stem(rom) + ".macro"))
+}
+
+/// Where finished speedruns of `rom` are logged.
+pub fn splits_log(rom: &Path) -> Option<PathBuf> {
+    let dir = create(data_dir()?.join("splits"))?;
+    Some(dir.join(stem(rom) + ".log"))
+}
+
+/// A fresh crash dump for the ROM with SHA-1 `rom`.
+pub fn crash_file(rom: &str, taken: u64) -> Option<PathBuf> {
+    let dir = create(data_dir()?.join("crashes"))?;
+    let name = if rom.is_empty() { "rom" } else { &rom[..8] };
+    Some(dir.join(format!("{}-{}.json", name, taken)))
+}
+
+/// A fresh screenshot file for `rom`, named after the time it was taken.
+pub fn screenshot_file(rom: &Path, taken: u64) -> Option<PathBuf> {
+    let dir = match UserDirs::new().and_then(|dirs| dirs.picture_dir().map(Path::to_path_buf)) {
+        Some(pictures) => create(pictures.join("chip8-rs"))?,
+        None => create(data_dir()?.join("screenshots"))?,
+    };
+    Some(dir.join(format!("{}-{}.bmp", stem(rom), taken)))
+}
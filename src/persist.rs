@@ -0,0 +1,96 @@
+// Keeps a range of memory across sessions, for homebrew games that store
+// their high scores in RAM. The range comes from --persist or `persist` in
+// the ROM's config section, and lives in persist/<rom name>.bin in the data
+// directory: it is written over the ROM after loading and saved whenever it
+// changes.
+use chip8_core::Chip8;
+use log::{info, warn};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// An inclusive range of addresses.
+#[derive(Clone, Copy)]
+pub struct MemoryRange {
+    start: usize,
+    end: usize,
+}
+
+/// Parses `<start>-<end>` in hex, both ends included, e.g. `2f0-2ff`.
+impl FromStr for MemoryRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = |s: &str| usize::from_str_radix(s.trim().trim_start_matches("0x"), 16).ok();
+        match s.split_once('-').map(|(start, end)| (hex(start), hex(end))) {
+            Some((Some(start), Some(end))) if start <= end => Ok(MemoryRange { start, end }),
+            _ => Err(format!("`{}` is not an address range like 2f0-2ff", s)),
+        }
+    }
+}
+
+impl MemoryRange {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+}
+
+pub struct Persist {
+    range: MemoryRange,
+    path: PathBuf,
+    // what the file holds
+    saved: Vec<u8>,
+}
+
+impl Persist {
+    pub fn new(range: MemoryRange, path: PathBuf, memory_len: usize) -> Result<Persist, String> {
+        if range.end >= memory_len {
+            return Err(format!(
+                "persisted range ends at {:#05x}, past the end of memory",
+                range.end
+            ));
+        }
+        Ok(Persist {
+            range,
+            path,
+            saved: Vec::new(),
+        })
+    }
+
+    /// Copy the saved bytes into `c8`, if there are any yet.
+    pub fn restore(&self, c8: &mut Chip8) {
+        let range = self.range.start..=self.range.end;
+        match fs::read(&self.path) {
+            Ok(data) if data.len() == range.clone().count() => {
+                c8.memory_mut()[range].copy_from_slice(&data);
+                info!(
+                    "restored {:#05x}-{:#05x} from {}",
+                    self.range.start,
+                    self.range.end,
+                    self.path.display()
+                );
+            }
+            Ok(_) => warn!(
+                "{} does not match the persisted range, ignoring it",
+                self.path.display()
+            ),
+            Err(_) => {}
+        }
+    }
+
+    /// Write the range to the file if it changed since the last time.
+    pub fn flush(&mut self, c8: &Chip8) {
+        let current = &c8.memory()[self.range.start..=self.range.end];
+        if current == self.saved.as_slice() {
+            return;
+        }
+        match fs::write(&self.path, current) {
+            Ok(()) => self.saved = current.to_vec(),
+            Err(e) => warn!("cannot write {}: {}", self.path.display(), e),
+        }
+    }
+}
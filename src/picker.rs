@@ -0,0 +1,144 @@
+use crate::archive;
+use crate::text::{draw_text, GLYPH_HEIGHT};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const SCALE: u32 = 2;
+const MARGIN: i32 = 8;
+const LINE_HEIGHT: i32 = ((GLYPH_HEIGHT + 2) * SCALE) as i32;
+
+/// The ROMs and archives in `dir`, sorted by name. Anything else kept next to
+/// them (saves, configs, screenshots) is left out.
+pub fn scan(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && archive::is_loadable(&path) {
+            roms.push(path);
+        }
+    }
+    roms.sort();
+    Ok(roms)
+}
+
+/// The files in `paths`, with directories replaced by the files in them and
+/// zip archives by the ROMs in them.
+pub fn expand(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            roms.extend(scan(path)?);
+        } else if archive::is_zip(path) && path.is_file() {
+            let entries = archive::roms(path)
+                .map_err(|e| io::Error::other(format!("{}: {}", path.display(), e)))?;
+            roms.extend(entries.iter().map(|entry| path.join(entry)));
+        } else {
+            roms.push(path.clone());
+        }
+    }
+    if roms.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no ROMs found"));
+    }
+    Ok(roms)
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn render(
+    canvas: &mut Canvas<Window>,
+    dir: &Path,
+    roms: &[PathBuf],
+    recent: usize,
+    selected: usize,
+) {
+    let (width, height) = canvas.output_size().unwrap();
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    draw_text(
+        canvas,
+        MARGIN,
+        MARGIN,
+        SCALE,
+        &format!("SELECT A ROM - {}", dir.display()),
+    );
+
+    let top = MARGIN + 2 * LINE_HEIGHT;
+    let rows = ((height as i32 - top - MARGIN) / LINE_HEIGHT).max(1) as usize;
+    // keep the selection in view by scrolling one page at a time
+    let first = (selected / rows) * rows;
+
+    for (i, rom) in roms.iter().enumerate().skip(first).take(rows) {
+        let y = top + (i - first) as i32 * LINE_HEIGHT;
+        if i == selected {
+            canvas.set_draw_color(Color::RGB(255, 255, 255));
+            canvas
+                .fill_rect(Rect::new(
+                    MARGIN / 2,
+                    y - SCALE as i32,
+                    width - MARGIN as u32,
+                    LINE_HEIGHT as u32,
+                ))
+                .unwrap();
+            canvas.set_draw_color(Color::RGB(0, 0, 0));
+        } else {
+            canvas.set_draw_color(Color::RGB(255, 255, 255));
+        }
+        let marker = if i < recent { "* " } else { "  " };
+        draw_text(
+            canvas,
+            MARGIN,
+            y,
+            SCALE,
+            &format!("{}{}", marker, display_name(rom)),
+        );
+    }
+    canvas.present();
+}
+
+/// Show a navigable list of `roms` and block until one is chosen. The first
+/// `recent` entries are the recently played ones and get a marker. Returns
+/// `None` if the user quits instead.
+pub fn pick(
+    canvas: &mut Canvas<Window>,
+    event_pump: &mut EventPump,
+    dir: &Path,
+    roms: &[PathBuf],
+    recent: usize,
+) -> Option<PathBuf> {
+    let mut selected = 0;
+    loop {
+        render(canvas, dir, roms, recent, selected);
+        match event_pump.wait_event() {
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => return None,
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => match key {
+                Keycode::Up => selected = selected.saturating_sub(1),
+                Keycode::Down => selected = (selected + 1).min(roms.len() - 1),
+                Keycode::Home => selected = 0,
+                Keycode::End => selected = roms.len() - 1,
+                Keycode::Return | Keycode::KpEnter => return Some(roms[selected].clone()),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,77 @@
+// Narrowing down where a game keeps a variable: start with every address,
+// then after each change in the game keep only the addresses whose value
+// moved the way the variable should have, e.g. `decreased` after losing a
+// life.
+use std::str::FromStr;
+
+#[derive(Clone, Copy)]
+pub enum Filter {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    Equals(u8),
+}
+
+impl Filter {
+    fn keep(self, before: u8, after: u8) -> bool {
+        match self {
+            Filter::Changed => after != before,
+            Filter::Unchanged => after == before,
+            Filter::Increased => after > before,
+            Filter::Decreased => after < before,
+            Filter::Equals(value) => after == value,
+        }
+    }
+}
+
+/// Parses `changed`, `unchanged`, `increased`, `decreased` or `eq <hex>`.
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let filter = match (parts.next(), parts.next()) {
+            (Some("changed"), None) => Filter::Changed,
+            (Some("unchanged"), None) => Filter::Unchanged,
+            (Some("increased"), None) => Filter::Increased,
+            (Some("decreased"), None) => Filter::Decreased,
+            (Some("eq"), Some(value)) => u8::from_str_radix(value.trim_start_matches("0x"), 16)
+                .map(Filter::Equals)
+                .map_err(|_| format!("bad value `{}`", value))?,
+            _ => return Err(format!("`{}` is not a search filter", s)),
+        };
+        match parts.next() {
+            None => Ok(filter),
+            Some(_) => Err(format!("`{}` is not a search filter", s)),
+        }
+    }
+}
+
+pub struct RamSearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<usize>,
+}
+
+impl RamSearch {
+    /// Start a search over all of `memory`.
+    pub fn new(memory: &[u8]) -> RamSearch {
+        RamSearch {
+            snapshot: memory.to_vec(),
+            candidates: (0..memory.len()).collect(),
+        }
+    }
+
+    /// Keep the candidates that pass `filter` comparing the previous snapshot
+    /// with `memory`, which becomes the next snapshot.
+    pub fn filter(&mut self, memory: &[u8], filter: Filter) {
+        let snapshot = &self.snapshot;
+        self.candidates
+            .retain(|&addr| filter.keep(snapshot[addr], memory[addr]));
+        self.snapshot = memory.to_vec();
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+}
@@ -0,0 +1,48 @@
+// The most recently opened ROMs, newest first, one absolute path per line in
+// recent.txt in the data directory. Feeds the top of the picker and --recent.
+use crate::archive;
+use crate::paths;
+use log::warn;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LIMIT: usize = 10;
+
+fn path() -> Option<PathBuf> {
+    Some(paths::data_dir()?.join("recent.txt"))
+}
+
+/// The remembered ROMs that still exist, newest first.
+pub fn load() -> Vec<PathBuf> {
+    let text = path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    text.lines()
+        .map(PathBuf::from)
+        .filter(|rom| rom.is_file() || archive::split(rom).is_some())
+        .collect()
+}
+
+/// Move `rom` to the front of the list. Failing to save it is not worth
+/// stopping the game for, so errors are only logged.
+pub fn add(rom: &Path) {
+    let Some(path) = path() else { return };
+    // only the archive part of a ROM inside a zip exists on disk
+    let rom = match archive::split(rom) {
+        Some((archive, entry)) => fs::canonicalize(&archive).map(|archive| archive.join(entry)),
+        None => fs::canonicalize(rom),
+    }
+    .unwrap_or_else(|_| rom.to_path_buf());
+    let mut roms = load();
+    roms.retain(|other| *other != rom);
+    roms.insert(0, rom);
+    roms.truncate(LIMIT);
+
+    let text: String = roms
+        .iter()
+        .map(|rom| format!("{}\n", rom.display()))
+        .collect();
+    if let Err(e) = fs::write(&path, text) {
+        warn!("cannot save recent ROMs to {}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,166 @@
+// Reading ROM files, with errors that say what went wrong instead of
+// panicking on the io::Error. Octo sources (.o8) are assembled on the way,
+// see octo.rs, zip and gzip files are unpacked, see archive.rs, and --patch
+// is applied afterwards, see patch.rs. A path of `-` reads the ROM from stdin.
+use crate::archive;
+use crate::octo::{self, Symbols};
+use crate::patch;
+use log::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::OnceLock;
+
+// exit codes, so scripts can tell the failures apart
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_NOT_A_FILE: i32 = 4;
+pub const EXIT_PERMISSION_DENIED: i32 = 5;
+pub const EXIT_UNREADABLE: i32 = 6;
+pub const EXIT_INVALID: i32 = 7;
+
+/// Whether `path` is `-`, for the ROM piped in on stdin.
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+// stdin can only be read once, but the ROM is loaded again on every reset
+fn read_stdin() -> Result<Vec<u8>, RomError> {
+    static DATA: OnceLock<Vec<u8>> = OnceLock::new();
+    if let Some(data) = DATA.get() {
+        return Ok(data.clone());
+    }
+    let mut data = Vec::new();
+    io::stdin()
+        .read_to_end(&mut data)
+        .map_err(|e| RomError::Unreadable(PathBuf::from("-"), e))?;
+    Ok(DATA.get_or_init(|| data).clone())
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    NotFound(PathBuf),
+    NotAFile(PathBuf),
+    PermissionDenied(PathBuf),
+    Unreadable(PathBuf, io::Error),
+    Assembly(PathBuf, String),
+    Archive(PathBuf, String),
+    /// A zip archive with more than one ROM, and their names
+    SeveralRoms(PathBuf, Vec<String>),
+}
+
+impl RomError {
+    fn from_io(path: &Path, e: io::Error) -> Self {
+        let path = path.to_path_buf();
+        match e.kind() {
+            ErrorKind::NotFound => RomError::NotFound(path),
+            ErrorKind::PermissionDenied => RomError::PermissionDenied(path),
+            _ => RomError::Unreadable(path, e),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RomError::NotFound(_) => EXIT_NOT_FOUND,
+            RomError::NotAFile(_) => EXIT_NOT_A_FILE,
+            RomError::PermissionDenied(_) => EXIT_PERMISSION_DENIED,
+            RomError::Unreadable(..) => EXIT_UNREADABLE,
+            RomError::Assembly(..) | RomError::Archive(..) | RomError::SeveralRoms(..) => {
+                EXIT_INVALID
+            }
+        }
+    }
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::NotFound(path) => write!(f, "ROM {} does not exist", path.display()),
+            RomError::NotAFile(path) => write!(f, "ROM {} is not a file", path.display()),
+            RomError::PermissionDenied(path) => {
+                write!(f, "no permission to read ROM {}", path.display())
+            }
+            RomError::Unreadable(path, e) => {
+                write!(f, "cannot read ROM {}: {}", path.display(), e)
+            }
+            RomError::Assembly(path, e) => write!(f, "{}: {}", path.display(), e),
+            RomError::Archive(path, e) => {
+                write!(f, "cannot unpack ROM {}: {}", path.display(), e)
+            }
+            RomError::SeveralRoms(path, roms) => write!(
+                f,
+                "{} holds several ROMs, name one like {}: {}",
+                path.display(),
+                path.join(&roms[0]).display(),
+                roms.join(", ")
+            ),
+        }
+    }
+}
+
+/// Read a ROM, along with its labels when it is assembled from source.
+pub fn load(path: &Path) -> Result<(Vec<u8>, Symbols), RomError> {
+    if is_stdin(path) {
+        return Ok((read_stdin()?, Symbols::default()));
+    }
+    let archive_error = |e| RomError::Archive(path.to_path_buf(), e);
+    // the name of the ROM itself, without a .gz
+    let mut name = path.to_path_buf();
+    let data = if let Some((archive, entry)) = archive::split(path) {
+        archive::read_entry(&archive, &entry).map_err(archive_error)?
+    } else {
+        let metadata = fs::metadata(path).map_err(|e| RomError::from_io(path, e))?;
+        if !metadata.is_file() {
+            return Err(RomError::NotAFile(path.to_path_buf()));
+        }
+        if archive::is_zip(path) {
+            let roms = archive::roms(path).map_err(archive_error)?;
+            return match roms.len() {
+                0 => Err(archive_error("there are no ROMs in it".to_string())),
+                1 => load(&path.join(&roms[0])),
+                _ => Err(RomError::SeveralRoms(path.to_path_buf(), roms)),
+            };
+        }
+        if archive::is_gzip(path) {
+            name.set_extension("");
+            archive::gunzip(path).map_err(archive_error)?
+        } else {
+            fs::read(path).map_err(|e| RomError::from_io(path, e))?
+        }
+    };
+    if name.extension().is_none_or(|ext| ext != "o8") {
+        return Ok((data, Symbols::default()));
+    }
+    let assembly_error = |e| RomError::Assembly(path.to_path_buf(), e);
+    let text = String::from_utf8(data).map_err(|_| assembly_error("not UTF-8 text".to_string()))?;
+    let program = octo::assemble(&text).map_err(assembly_error)?;
+    Ok((program.rom, program.symbols))
+}
+
+/// Load a ROM, or explain why not and exit.
+pub fn load_or_exit(path: &Path) -> (Vec<u8>, Symbols) {
+    load(path).unwrap_or_else(|e| {
+        error!("{}", e);
+        error!("usage: chip8-rs [OPTIONS] [ROM], see --help");
+        exit(e.exit_code());
+    })
+}
+
+/// Apply the IPS or BPS patch in `path` to a loaded ROM, or explain why not
+/// and exit.
+pub fn patch_or_exit(data: &[u8], path: &Path) -> Vec<u8> {
+    let patched = fs::read(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))
+        .and_then(|patch| {
+            patch::apply(data, &patch).map_err(|e| format!("{}: {}", path.display(), e))
+        });
+    patched.unwrap_or_else(|e| {
+        error!("{}", e);
+        exit(EXIT_INVALID);
+    })
+}
+
+pub fn read_or_exit(path: &Path) -> Vec<u8> {
+    load_or_exit(path).0
+}
@@ -0,0 +1,71 @@
+// Haptic feedback on game controllers while the sound timer is running.
+use log::info;
+use sdl2::haptic::Haptic;
+use sdl2::{GameControllerSubsystem, HapticSubsystem, Sdl};
+
+const STRENGTH: f32 = 0.75;
+// SDL_HAPTIC_INFINITY, the effect runs until `rumble_stop`
+const DURATION: u32 = u32::MAX;
+
+pub struct Rumble {
+    controllers: GameControllerSubsystem,
+    haptics: HapticSubsystem,
+    device: Option<Haptic>,
+    active: bool,
+}
+
+impl Rumble {
+    pub fn new(sdl_context: &Sdl) -> Result<Self, String> {
+        let mut rumble = Rumble {
+            controllers: sdl_context.game_controller()?,
+            haptics: sdl_context.haptic()?,
+            device: None,
+            active: false,
+        };
+        rumble.rescan();
+        if rumble.device.is_none() {
+            info!("rumble: no controller with haptic support connected yet");
+        }
+        Ok(rumble)
+    }
+
+    /// Try to use the controller at joystick `index`, called on startup and
+    /// whenever a controller is plugged in.
+    pub fn connect(&mut self, index: u32) {
+        if self.device.is_some() || !self.controllers.is_game_controller(index) {
+            return;
+        }
+        if let Ok(device) = self.haptics.open_from_joystick_id(index) {
+            let name = self
+                .controllers
+                .name_for_index(index)
+                .unwrap_or_else(|_| "controller".to_string());
+            info!("rumble: using {}", name);
+            self.device = Some(device);
+            self.active = false;
+        }
+    }
+
+    /// Drop the current device and pick the first usable one still connected,
+    /// called when a controller is unplugged.
+    pub fn rescan(&mut self) {
+        self.device = None;
+        for index in 0..self.controllers.num_joysticks().unwrap_or(0) {
+            self.connect(index);
+        }
+    }
+
+    pub fn update(&mut self, beeping: bool) {
+        if beeping == self.active {
+            return;
+        }
+        if let Some(device) = self.device.as_mut() {
+            if beeping {
+                device.rumble_play(STRENGTH, DURATION);
+            } else {
+                device.rumble_stop();
+            }
+        }
+        self.active = beeping;
+    }
+}
@@ -0,0 +1,33 @@
+// Screenshots of the first instance, drawn from its pixel buffer rather than
+// read back from the window so they come out the same on every renderer.
+use crate::config::Palette;
+use chip8_core::{Chip8, HEIGHT, WIDTH};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use std::path::Path;
+
+/// Write the screen of `c8` as a BMP, with every Chip-8 pixel `scale` pixels
+/// wide.
+pub fn save(c8: &Chip8, palette: &Palette, scale: u32, path: &Path) -> Result<(), String> {
+    let scale = scale as usize;
+    let (width, height) = (WIDTH * scale, HEIGHT * scale);
+    let mut data = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let rgb = if c8.pixel_buffer()[y / scale][x / scale] {
+                palette.foreground
+            } else {
+                palette.background
+            };
+            data.extend_from_slice(&[rgb.0, rgb.1, rgb.2]);
+        }
+    }
+    let surface = Surface::from_data(
+        &mut data,
+        width as u32,
+        height as u32,
+        (width * 3) as u32,
+        PixelFormatEnum::RGB24,
+    )?;
+    surface.save_bmp(path)
+}
@@ -0,0 +1,144 @@
+// Rhai scripts (https://rhai.rs) that run alongside the game, built with the
+// `scripting` feature. A script defines any of the hooks
+//
+//   fn on_frame() { ... }                   after every 60Hz frame
+//   fn on_instruction(pc, opcode) { ... }   after every instruction
+//   fn on_key(key, pressed) { ... }         when a keypad key changes
+//
+// and talks to the first instance through peek(addr) / poke(addr, value),
+// reg(n) / set_reg(n, value), index(), pc(), key(n) and set_key(n, pressed).
+// Rhai functions can't see variables outside themselves, so anything kept
+// between calls goes in `this`, an object map shared by all hooks.
+use chip8_core::Chip8;
+use log::error;
+use rhai::{Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
+
+type Result<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+const HOOKS: [&str; 3] = ["on_frame", "on_instruction", "on_key"];
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    this: Dynamic,
+    // the machine the API functions see, swapped with the real one for the
+    // duration of a hook
+    machine: Rc<RefCell<Chip8>>,
+    hooks: Vec<&'static str>,
+}
+
+fn checked(index: i64, len: usize, what: &str) -> Result<usize> {
+    usize::try_from(index)
+        .ok()
+        .filter(|&i| i < len)
+        .ok_or_else(|| format!("{} {} out of range", what, index).into())
+}
+
+fn register(engine: &mut Engine, machine: &Rc<RefCell<Chip8>>) {
+    let m = machine.clone();
+    engine.register_fn("peek", move |addr: i64| -> Result<i64> {
+        let c8 = m.borrow();
+        Ok(c8.memory()[checked(addr, c8.memory().len(), "address")?] as i64)
+    });
+    let m = machine.clone();
+    engine.register_fn("poke", move |addr: i64, value: i64| -> Result<()> {
+        let mut c8 = m.borrow_mut();
+        let addr = checked(addr, c8.memory().len(), "address")?;
+        c8.memory_mut()[addr] = value as u8;
+        Ok(())
+    });
+    let m = machine.clone();
+    engine.register_fn("reg", move |n: i64| -> Result<i64> {
+        Ok(m.borrow().registers()[checked(n, 16, "register")?] as i64)
+    });
+    let m = machine.clone();
+    engine.register_fn("set_reg", move |n: i64, value: i64| -> Result<()> {
+        m.borrow_mut().registers_mut()[checked(n, 16, "register")?] = value as u8;
+        Ok(())
+    });
+    let m = machine.clone();
+    engine.register_fn("index", move || m.borrow().index() as i64);
+    let m = machine.clone();
+    engine.register_fn("pc", move || m.borrow().pc() as i64);
+    let m = machine.clone();
+    engine.register_fn("key", move |n: i64| -> Result<bool> {
+        Ok(m.borrow().keypad()[checked(n, 16, "key")?])
+    });
+    let m = machine.clone();
+    engine.register_fn("set_key", move |n: i64, pressed: bool| -> Result<()> {
+        m.borrow_mut().set_key(checked(n, 16, "key")?, pressed);
+        Ok(())
+    });
+}
+
+impl Script {
+    /// Compile the script in `path` and run its top level statements.
+    pub fn load(path: &Path) -> std::result::Result<Script, String> {
+        let machine = Rc::new(RefCell::new(Chip8::new()));
+        let mut engine = Engine::new();
+        register(&mut engine, &machine);
+        let fail = |e: Box<EvalAltResult>| format!("{}: {}", path.display(), e);
+
+        let ast = engine.compile_file(path.to_path_buf()).map_err(fail)?;
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast).map_err(fail)?;
+        let hooks = HOOKS
+            .iter()
+            .copied()
+            .filter(|hook| ast.iter_functions().any(|f| f.name == *hook))
+            .collect();
+        Ok(Script {
+            engine,
+            ast,
+            scope,
+            this: Map::new().into(),
+            machine,
+            hooks,
+        })
+    }
+
+    fn call(&mut self, c8: &mut Chip8, hook: &'static str, args: impl FuncArgs) {
+        if !self.hooks.contains(&hook) {
+            return;
+        }
+        mem::swap(c8, &mut self.machine.borrow_mut());
+        let options = rhai::CallFnOptions::new()
+            .eval_ast(false)
+            .rewind_scope(false)
+            .bind_this_ptr(&mut self.this);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            hook,
+            args,
+        );
+        mem::swap(c8, &mut self.machine.borrow_mut());
+
+        // one error tends to repeat every frame, so stop calling that hook
+        if let Err(e) = result {
+            error!("script {}: {}", hook, e);
+            self.hooks.retain(|h| *h != hook);
+        }
+    }
+
+    pub fn on_frame(&mut self, c8: &mut Chip8) {
+        self.call(c8, "on_frame", ());
+    }
+
+    pub fn on_instruction(&mut self, c8: &mut Chip8) {
+        if let Some((pc, opcode)) = c8.recent_instructions().last() {
+            self.call(c8, "on_instruction", (pc as i64, opcode as i64));
+        }
+    }
+
+    pub fn on_key(&mut self, c8: &mut Chip8, key: usize, pressed: bool) {
+        self.call(c8, "on_key", (key as i64, pressed));
+    }
+}
@@ -0,0 +1,67 @@
+// Reports a program rewriting its own code, for --self-modifying. The core
+// notices writes to bytes it has already executed while it counts memory
+// accesses, this logs them with the source labels of .o8 ROMs.
+use crate::octo::Symbols;
+use chip8_core::Chip8;
+use log::{debug, info, warn};
+use std::collections::BTreeSet;
+
+fn location(addr: usize, symbols: &Symbols) -> String {
+    match symbols.describe(addr) {
+        Some(label) => format!("{:#05x} ({})", addr, label),
+        None => format!("{:#05x}", addr),
+    }
+}
+
+#[derive(Default)]
+pub struct SelfModifying {
+    // addresses rewritten so far
+    seen: BTreeSet<usize>,
+}
+
+impl SelfModifying {
+    /// Log the writes to code since the last call. The first one to an
+    /// address is a warning, later ones only show up in the debug log since
+    /// some games patch the same instruction every frame.
+    pub fn log(&mut self, c8: &mut Chip8, symbols: &Symbols) {
+        for write in c8.take_code_writes() {
+            let message = format!(
+                "{} wrote {:#04x} over code at {}",
+                location(write.pc, symbols),
+                write.value,
+                location(write.addr, symbols)
+            );
+            if self.seen.insert(write.addr) {
+                warn!("{}", message);
+            } else {
+                debug!("{}", message);
+            }
+        }
+    }
+
+    /// Log the rewritten addresses as ranges.
+    pub fn summary(&self) {
+        if self.seen.is_empty() {
+            info!("no self-modifying code found");
+            return;
+        }
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for &addr in &self.seen {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == addr => *end = addr,
+                _ => ranges.push((addr, addr)),
+            }
+        }
+        let ranges: Vec<String> = ranges
+            .iter()
+            .map(|&(start, end)| {
+                if start == end {
+                    format!("{:03x}", start)
+                } else {
+                    format!("{:03x}-{:03x}", start, end)
+                }
+            })
+            .collect();
+        info!("code was rewritten at {}", ranges.join(", "));
+    }
+}
@@ -0,0 +1,228 @@
+// Streams the screen over WebSocket, see assets/viewer.html for a browser
+// client. --serve runs headless and takes keypad input back from the clients,
+// --spectate streams a normal windowed run to viewers that can only watch.
+//
+// Server to client, binary messages:
+//   'F' followed by 256 bytes: the full 64x32 screen, one bit per pixel, row
+//       major, most significant bit first. Sent once when a client connects,
+//       as of the last 'D' the other clients were sent.
+//   'D' followed by big endian u16 pixel indices (y * 64 + x) that flipped
+//       since the previous message.
+//   'S' followed by one byte, 1 while the buzzer sounds and 0 when it stops.
+//       Sent when a client connects and on every change.
+//
+// Client to server, text messages:
+//   "down <key>" / "up <key>" with the keypad key as a hex digit, e.g. "down a"
+//   Ignored from spectators.
+use crate::headless::Limits;
+use chip8_core::{Chip8, HEIGHT, WIDTH};
+use log::info;
+use std::io::{self, ErrorKind};
+use std::mem;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::process::exit;
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::NoCallback;
+use tungstenite::handshake::MidHandshake;
+use tungstenite::{Error, HandshakeError, Message, ServerHandshake, WebSocket};
+
+type Client = WebSocket<TcpStream>;
+type Handshake = MidHandshake<ServerHandshake<TcpStream, NoCallback>>;
+
+// connections that have not finished the handshake by then are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn pack(pixels: &[Vec<bool>]) -> Vec<u8> {
+    let mut out = vec![b'F'];
+    for row in pixels {
+        for byte in row.chunks(8) {
+            out.push(byte.iter().fold(0, |acc, &p| (acc << 1) | p as u8));
+        }
+    }
+    out
+}
+
+fn diff(previous: &[Vec<bool>], current: &[Vec<bool>]) -> Vec<u8> {
+    let mut out = vec![b'D'];
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            if previous[y][x] != current[y][x] {
+                out.extend_from_slice(&((y * WIDTH + x) as u16).to_be_bytes());
+            }
+        }
+    }
+    out
+}
+
+fn parse_key_event(text: &str) -> Option<(usize, bool)> {
+    let mut parts = text.split_whitespace();
+    let pressed = match parts.next()? {
+        "down" => true,
+        "up" => false,
+        _ => return None,
+    };
+    let key = usize::from_str_radix(parts.next()?, 16).ok()?;
+    if key > 0xF {
+        return None;
+    }
+    Some((key, pressed))
+}
+
+fn is_would_block(e: &Error) -> bool {
+    matches!(e, Error::Io(e) if e.kind() == ErrorKind::WouldBlock)
+}
+
+// returns false once the client should be dropped
+fn send(client: &mut Client, data: Vec<u8>) -> bool {
+    match client.send(Message::binary(data)) {
+        Ok(()) => true,
+        Err(ref e) if is_would_block(e) => true,
+        Err(_) => false,
+    }
+}
+
+fn sound(beeping: bool) -> Vec<u8> {
+    vec![b'S', beeping as u8]
+}
+
+// drain pending messages, returns false once the client has gone away
+fn poll_input(client: &mut Client, mut c8: Option<&mut Chip8>) -> bool {
+    loop {
+        match client.read() {
+            Ok(Message::Text(text)) => {
+                if let (Some(c8), Some((key, pressed))) = (c8.as_mut(), parse_key_event(&text)) {
+                    c8.set_key(key, pressed);
+                }
+            }
+            Ok(Message::Close(_)) => return false,
+            Ok(_) => {}
+            Err(ref e) if is_would_block(e) => return true,
+            Err(_) => return false,
+        }
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+    // connections still in the handshake, polled along with everything else
+    // so a slow or idle one cannot hold up the game
+    pending: Vec<(Handshake, Instant)>,
+    clients: Vec<Client>,
+    // what the clients have been sent so far
+    last_sent: Vec<Vec<bool>>,
+    beeping: bool,
+    spectators: bool,
+}
+
+impl Server {
+    /// Listen on `addr`. Keypad input from the clients is ignored when they
+    /// are `spectators`.
+    pub fn bind<A: ToSocketAddrs>(addr: A, c8: &Chip8, spectators: bool) -> io::Result<Server> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        info!("serving on ws://{}", listener.local_addr()?);
+        Ok(Server {
+            listener,
+            pending: Vec::new(),
+            clients: Vec::new(),
+            last_sent: c8.pixel_buffer().to_vec(),
+            beeping: false,
+            spectators,
+        })
+    }
+
+    /// Take new connections and apply the input of the connected clients.
+    pub fn poll(&mut self, c8: &mut Chip8) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if stream.set_nonblocking(true).is_ok() {
+                        self.handshake(tungstenite::accept(stream), Instant::now());
+                    }
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        for (handshake, started) in mem::take(&mut self.pending) {
+            if started.elapsed() < HANDSHAKE_TIMEOUT {
+                self.handshake(handshake.handshake(), started);
+            }
+        }
+        let spectators = self.spectators;
+        self.clients.retain_mut(|client| {
+            poll_input(client, if spectators { None } else { Some(&mut *c8) })
+        });
+        Ok(())
+    }
+
+    fn handshake(
+        &mut self,
+        result: Result<Client, HandshakeError<ServerHandshake<TcpStream, NoCallback>>>,
+        started: Instant,
+    ) {
+        match result {
+            Ok(client) => self.welcome(client),
+            Err(HandshakeError::Interrupted(handshake)) => self.pending.push((handshake, started)),
+            Err(HandshakeError::Failure(_)) => {}
+        }
+    }
+
+    // the screen as the other clients have it, so the next diff applies
+    fn welcome(&mut self, mut client: Client) {
+        if let Ok(peer) = client.get_ref().peer_addr() {
+            info!("viewer connected from {}", peer);
+        }
+        if send(&mut client, pack(&self.last_sent)) && send(&mut client, sound(self.beeping)) {
+            self.clients.push(client);
+        }
+    }
+
+    /// Send whatever changed on the screen and the buzzer since the last
+    /// update.
+    pub fn update(&mut self, c8: &Chip8) {
+        let frame = diff(&self.last_sent, c8.pixel_buffer());
+        if frame.len() > 1 {
+            self.clients
+                .retain_mut(|client| send(client, frame.clone()));
+            self.last_sent = c8.pixel_buffer().to_vec();
+        }
+        let beeping = c8.sound_timer() > 0;
+        if beeping != self.beeping {
+            self.clients
+                .retain_mut(|client| send(client, sound(beeping)));
+            self.beeping = beeping;
+        }
+    }
+}
+
+/// Serve until `limits` are reached, running `speed` instructions a frame at
+/// 60 frames a second like the window does.
+pub fn run<A: ToSocketAddrs>(
+    addr: A,
+    mut c8: Chip8,
+    speed: u32,
+    mut limits: Limits,
+) -> io::Result<()> {
+    let mut server = Server::bind(addr, &c8, false)?;
+    let mut next_frame = Instant::now();
+    while !limits.reached() {
+        server.poll(&mut c8)?;
+
+        for _ in 0..speed {
+            if let Err(e) = c8.tick_cpu() {
+                crate::report_crash(&c8, speed, e);
+                exit(1);
+            }
+            limits.count();
+            if limits.reached() {
+                break;
+            }
+        }
+        c8.tick_60hz();
+        server.update(&c8);
+
+        crate::wait_for_frame(&mut next_frame);
+    }
+    Ok(())
+}
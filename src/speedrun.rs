@@ -0,0 +1,244 @@
+// Speedrun timer (--splits FILE): starts on the first key press after the ROM
+// is loaded or reset and splits automatically when the game's memory says so.
+// One split per line, taken in order:
+//
+//   <name> <location> <test> [<value>]
+//
+// where the location is a hex address or a register (v0 to vf), the test is
+// one of == != < > <= >= with a hex value, or `changed` / `increased` without
+// one, e.g. `level-2 2f6 == 02` or `bonus v5 increased`. Blank lines and lines
+// starting with `#` are ignored.
+//
+// Time is counted in emulated frames at 60 per second, so pausing and slow
+// hosts don't count. Finished runs are appended to splits/<rom name>.log in
+// the data directory, one line per run with the time of every split.
+use crate::text::{draw_text, GLYPH_WIDTH};
+use chip8_core::Chip8;
+use log::{info, warn};
+use sdl2::pixels::Color;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TEXT_SCALE: u32 = 2;
+
+#[derive(Clone, Copy)]
+enum Location {
+    Memory(usize),
+    Register(usize),
+}
+
+#[derive(Clone, Copy)]
+enum Test {
+    Equal(u8),
+    NotEqual(u8),
+    Less(u8),
+    Greater(u8),
+    AtMost(u8),
+    AtLeast(u8),
+    Changed,
+    Increased,
+}
+
+struct Split {
+    name: String,
+    location: Location,
+    test: Test,
+}
+
+pub struct Speedrun {
+    splits: Vec<Split>,
+    // frame each split was hit on, counted from the start
+    times: Vec<u64>,
+    started: Option<u64>,
+    // the frame the timer shows
+    now: u64,
+    // the value of the next split's location after the previous frame
+    last: Option<u8>,
+    log: Option<PathBuf>,
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+    usize::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+}
+
+fn parse_split(line: &str, memory_len: usize) -> Result<Split, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 3 {
+        return Err("expected <name> <location> <test> [<value>]".to_string());
+    }
+    let location = match fields[1].strip_prefix('v') {
+        Some(reg) if reg.len() == 1 => Location::Register(
+            parse_hex(reg).ok_or_else(|| format!("bad register `{}`", fields[1]))?,
+        ),
+        _ => Location::Memory(
+            parse_hex(fields[1])
+                .filter(|&addr| addr < memory_len)
+                .ok_or_else(|| format!("bad address `{}`", fields[1]))?,
+        ),
+    };
+    let value = || -> Result<u8, String> {
+        let field = fields.get(3).ok_or("missing value")?;
+        parse_hex(field)
+            .filter(|&value| value <= 0xFF)
+            .map(|value| value as u8)
+            .ok_or_else(|| format!("bad value `{}`", field))
+    };
+    let test = match fields[2] {
+        "==" => Test::Equal(value()?),
+        "!=" => Test::NotEqual(value()?),
+        "<" => Test::Less(value()?),
+        ">" => Test::Greater(value()?),
+        "<=" => Test::AtMost(value()?),
+        ">=" => Test::AtLeast(value()?),
+        "changed" => Test::Changed,
+        "increased" => Test::Increased,
+        other => return Err(format!("unknown test `{}`", other)),
+    };
+    Ok(Split {
+        name: fields[0].to_string(),
+        location,
+        test,
+    })
+}
+
+/// `frames` as m:ss.cc
+fn format_time(frames: u64) -> String {
+    let centis = frames * 100 / 60;
+    format!(
+        "{}:{:02}.{:02}",
+        centis / 6000,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+impl Speedrun {
+    /// Read the splits in `path`. Finished runs are logged to `log`.
+    pub fn load(path: &Path, memory_len: usize, log: Option<PathBuf>) -> Result<Speedrun, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let mut splits = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            splits.push(
+                parse_split(line, memory_len)
+                    .map_err(|e| format!("{}: line {}: {}", path.display(), i + 1, e))?,
+            );
+        }
+        if splits.is_empty() {
+            return Err(format!("{} has no splits", path.display()));
+        }
+        Ok(Speedrun {
+            splits,
+            times: Vec::new(),
+            started: None,
+            now: 0,
+            last: None,
+            log,
+        })
+    }
+
+    /// Start over, waiting for the first key press again.
+    pub fn reset(&mut self) {
+        self.times.clear();
+        self.started = None;
+        self.now = 0;
+        self.last = None;
+    }
+
+    fn finished(&self) -> bool {
+        self.times.len() == self.splits.len()
+    }
+
+    /// Check for the start and the next split after `frame` ran.
+    pub fn update(&mut self, frame: u64, c8: &Chip8) {
+        if self.finished() {
+            return;
+        }
+        let Some(started) = self.started else {
+            if c8.keypad().iter().any(|&held| held) {
+                self.started = Some(frame);
+                info!("speedrun timer started");
+            }
+            return;
+        };
+        self.now = frame - started;
+
+        let split = &self.splits[self.times.len()];
+        let value = match split.location {
+            Location::Memory(addr) => c8.memory()[addr],
+            Location::Register(reg) => c8.registers()[reg],
+        };
+        let hit = match split.test {
+            Test::Equal(n) => value == n,
+            Test::NotEqual(n) => value != n,
+            Test::Less(n) => value < n,
+            Test::Greater(n) => value > n,
+            Test::AtMost(n) => value <= n,
+            Test::AtLeast(n) => value >= n,
+            Test::Changed => self.last.is_some_and(|last| value != last),
+            Test::Increased => self.last.is_some_and(|last| value > last),
+        };
+        if !hit {
+            self.last = Some(value);
+            return;
+        }
+        info!("split {}: {}", split.name, format_time(self.now));
+        self.times.push(self.now);
+        // the next split starts watching from the following frame
+        self.last = None;
+        if self.finished() {
+            info!("finished in {}", format_time(self.now));
+            self.write_log();
+        }
+    }
+
+    fn write_log(&self) {
+        let Some(path) = &self.log else { return };
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_secs());
+        let mut line = at.to_string();
+        for (split, &time) in self.splits.iter().zip(&self.times) {
+            line.push_str(&format!(" {}={}", split.name, format_time(time)));
+        }
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        match written {
+            Ok(()) => info!("logged the run to {}", path.display()),
+            Err(e) => warn!("cannot write {}: {}", path.display(), e),
+        }
+    }
+
+    /// Draw the running time, and the last split, in the top right corner of
+    /// a screen `width` pixels wide.
+    pub fn draw(&self, canvas: &mut Canvas<Window>, width: u32) {
+        let right = |text: &str| {
+            let advance = (GLYPH_WIDTH + 1) * TEXT_SCALE;
+            width as i32 - (text.len() as u32 * advance + TEXT_SCALE) as i32
+        };
+        let time = format_time(self.now);
+        canvas.set_draw_color(if self.finished() {
+            Color::RGB(64, 224, 64)
+        } else {
+            Color::RGB(255, 255, 255)
+        });
+        draw_text(canvas, right(&time), 2, TEXT_SCALE, &time);
+        if let Some(&last) = self.times.last() {
+            let split = &self.splits[self.times.len() - 1];
+            let text = format!("{} {}", split.name, format_time(last));
+            canvas.set_draw_color(Color::RGB(160, 160, 160));
+            draw_text(canvas, right(&text), 16, TEXT_SCALE, &text);
+        }
+    }
+}
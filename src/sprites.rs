@@ -0,0 +1,90 @@
+// --sprite-viewer: a second window showing memory as sprites, the way DXYN
+// would draw it. Bytes are laid out top to bottom in strips of 32, one row of
+// 8 pixels per byte, strips side by side. The bytes at I are tinted red (up
+// to 15, the tallest sprite) so the sprite the game is about to draw stands
+// out. Without a range the view follows I around.
+use crate::persist::MemoryRange;
+use crate::text::{draw_text, GLYPH_HEIGHT};
+use chip8_core::Chip8;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+
+const STRIP_LEN: usize = 32;
+// bytes shown when following I
+const FOLLOW_LEN: usize = 256;
+const PIXEL: u32 = 4;
+const GAP: u32 = 2 * PIXEL;
+const HEADER: u32 = (GLYPH_HEIGHT + 2) * 2;
+
+pub struct SpriteViewer {
+    canvas: Canvas<Window>,
+    range: Option<MemoryRange>,
+}
+
+impl SpriteViewer {
+    pub fn new(video: &VideoSubsystem, range: Option<MemoryRange>) -> Result<SpriteViewer, String> {
+        let len = range.map_or(FOLLOW_LEN, |range| range.end() - range.start() + 1);
+        let strips = len.div_ceil(STRIP_LEN) as u32;
+        let window = video
+            .window(
+                "sprites",
+                strips * (8 * PIXEL + GAP) + GAP,
+                HEADER + STRIP_LEN as u32 * PIXEL + GAP,
+            )
+            .build()
+            .map_err(|e| e.to_string())?;
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        Ok(SpriteViewer { canvas, range })
+    }
+
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// Redraw from the current contents of memory.
+    pub fn update(&mut self, c8: &Chip8) {
+        let memory = c8.memory();
+        let (start, end) = match self.range {
+            Some(range) => {
+                let last = memory.len() - 1;
+                (range.start().min(last), range.end().min(last))
+            }
+            // keep the view still while I moves around within a strip, I can
+            // point past the end of memory so the view stops at the last strip
+            None => {
+                let index = c8.index().min(memory.len() - 1);
+                let start = index / STRIP_LEN * STRIP_LEN;
+                (start, (start + FOLLOW_LEN).min(memory.len()) - 1)
+            }
+        };
+        let sprite = c8.index()..c8.index() + 15;
+
+        self.canvas.set_draw_color(Color::RGB(0x20, 0x20, 0x20));
+        self.canvas.clear();
+        self.canvas.set_draw_color(Color::RGB(255, 255, 255));
+        let label = format!("{:03X}-{:03X} I {:03X}", start, end, c8.index());
+        draw_text(&mut self.canvas, GAP as i32, 2, 2, &label);
+
+        for (offset, &byte) in memory[start..=end].iter().enumerate() {
+            let addr = start + offset;
+            let x = GAP + (offset / STRIP_LEN) as u32 * (8 * PIXEL + GAP);
+            let y = HEADER + (offset % STRIP_LEN) as u32 * PIXEL;
+            let (on, off) = if sprite.contains(&addr) {
+                (Color::RGB(255, 96, 96), Color::RGB(96, 0, 0))
+            } else {
+                (Color::RGB(255, 255, 255), Color::RGB(0, 0, 0))
+            };
+            for bit in 0..8 {
+                let lit = byte & (0x80 >> bit) != 0;
+                self.canvas.set_draw_color(if lit { on } else { off });
+                self.canvas
+                    .fill_rect(Rect::new((x + bit * PIXEL) as i32, y as i32, PIXEL, PIXEL))
+                    .unwrap();
+            }
+        }
+        self.canvas.present();
+    }
+}
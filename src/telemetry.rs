@@ -0,0 +1,82 @@
+// Run statistics for --telemetry, written as JSON on exit (and every
+// --telemetry-interval seconds) so performance can be charted across
+// versions.
+use chip8_core::{decode, Chip8, RomId};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Serialize)]
+pub struct Telemetry {
+    version: &'static str,
+    /// SHA-1 of the ROM being played
+    rom: String,
+    instructions: u64,
+    /// Executions per opcode pattern, e.g. "DXYN"
+    opcodes: BTreeMap<&'static str, u64>,
+    draw_calls: u64,
+    /// Emulated 60Hz frames, not counting time spent paused
+    frames: u64,
+    /// Frames where the screen changed and was redrawn
+    frames_rendered: u64,
+    /// Time spent emulating and drawing a frame, without the wait for the
+    /// next one
+    average_frame_ms: f64,
+    /// How long the buzzer sounded
+    audio_seconds: f64,
+    #[serde(skip)]
+    frame_time: Duration,
+}
+
+impl Telemetry {
+    pub fn new() -> Telemetry {
+        Telemetry {
+            version: env!("CARGO_PKG_VERSION"),
+            rom: String::new(),
+            instructions: 0,
+            opcodes: BTreeMap::new(),
+            draw_calls: 0,
+            frames: 0,
+            frames_rendered: 0,
+            average_frame_ms: 0.0,
+            audio_seconds: 0.0,
+            frame_time: Duration::ZERO,
+        }
+    }
+
+    pub fn set_rom(&mut self, rom: &RomId) {
+        self.rom = rom.sha1_hex();
+    }
+
+    /// Record the instruction the last `tick_cpu` executed.
+    pub fn record_instruction(&mut self, c8: &Chip8) {
+        if let Some((_, oc)) = c8.recent_instructions().last() {
+            let pattern = decode(oc).pattern();
+            *self.opcodes.entry(pattern).or_insert(0) += 1;
+            self.instructions += 1;
+            if pattern == "DXYN" {
+                self.draw_calls += 1;
+            }
+        }
+    }
+
+    pub fn record_frame(&mut self, took: Duration, rendered: bool, beeping: bool) {
+        self.frames += 1;
+        self.frame_time += took;
+        self.frames_rendered += rendered as u64;
+        if beeping {
+            self.audio_seconds += 1.0 / 60.0;
+        }
+    }
+
+    pub fn write(&mut self, path: &Path) -> Result<(), String> {
+        if self.frames > 0 {
+            self.average_frame_ms = self.frame_time.as_secs_f64() * 1000.0 / self.frames as f64;
+        }
+        // the report only holds strings and numbers
+        let json = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, json + "\n").map_err(|e| format!("cannot write {}: {}", path.display(), e))
+    }
+}
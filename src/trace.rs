@@ -0,0 +1,177 @@
+// `trace`: compares execution against a per-instruction trace from another
+// emulator. Each line describes the machine right before an instruction runs:
+//
+//   <pc> <opcode> <V0> ... <VF> <I>
+//
+// as hex numbers separated by whitespace. Labels in front of a value are
+// skipped, so `PC:0200 OP:00E0 V0:00 ...` or `pc=200 ...` parse as well.
+// Blank lines and lines starting with # are ignored. `--write` produces the
+// same format from this emulator.
+//
+// The timers are ticked once every --speed instructions, like a frame of the
+// windowed run, so the reference should come from an emulator running the
+// same number of instructions per 60Hz frame or DT and ST drift apart.
+use crate::cli::TraceArgs;
+use crate::headless::Clock;
+use chip8_core::{decode, Chip8, Opcode};
+use log::error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::process::exit;
+
+#[derive(PartialEq)]
+struct Snapshot {
+    pc: usize,
+    opcode: Opcode,
+    registers: Vec<u8>,
+    index: usize,
+}
+
+impl Snapshot {
+    fn of(c8: &Chip8) -> Self {
+        let pc = c8.pc();
+        let memory = c8.memory();
+        // a pc at the very end of memory has no opcode, let step report it
+        let opcode = match (memory.get(pc), memory.get(pc + 1)) {
+            (Some(&hi), Some(&lo)) => u16::from_be_bytes([hi, lo]),
+            _ => 0,
+        };
+        Snapshot {
+            pc,
+            opcode,
+            registers: c8.registers().to_vec(),
+            index: c8.index(),
+        }
+    }
+
+    fn parse(line: &str) -> Result<Self, String> {
+        let values = line
+            .split_whitespace()
+            .map(|field| {
+                let value = field.rsplit([':', '=']).next().unwrap();
+                usize::from_str_radix(value.trim_start_matches("0x"), 16)
+                    .map_err(|_| format!("`{}` is not a hex value", field))
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+        if values.len() != 19 {
+            return Err(format!(
+                "expected 19 values (pc, opcode, V0-VF, I), found {}",
+                values.len()
+            ));
+        }
+        Ok(Snapshot {
+            pc: values[0],
+            opcode: values[1] as Opcode,
+            registers: values[2..18].iter().map(|&v| v as u8).collect(),
+            index: values[18],
+        })
+    }
+
+    fn line(&self) -> String {
+        format!(
+            "{:03x} {:04x} {} {:03x}",
+            self.pc,
+            self.opcode,
+            self.hex_registers(),
+            self.index
+        )
+    }
+
+    fn hex_registers(&self) -> String {
+        let registers: Vec<String> = self
+            .registers
+            .iter()
+            .map(|v| format!("{:02x}", v))
+            .collect();
+        registers.join(" ")
+    }
+
+    fn print(&self, who: &str) {
+        println!(
+            "  {:<9} pc {:#05x}  {:04x} {:?}",
+            who,
+            self.pc,
+            self.opcode,
+            decode(self.opcode)
+        );
+        println!(
+            "  {:<9} V0-VF {}  I {:#05x}",
+            "",
+            self.hex_registers(),
+            self.index
+        );
+    }
+}
+
+fn write_trace(c8: &mut Chip8, args: &TraceArgs, path: &std::path::Path) {
+    let file = File::create(path).unwrap_or_else(|e| {
+        error!("cannot create {}: {}", path.display(), e);
+        exit(1);
+    });
+    let mut out = BufWriter::new(file);
+    let mut clock = Clock::new(args.speed);
+    for _ in 0..args.cycles {
+        writeln!(out, "{}", Snapshot::of(c8).line()).unwrap();
+        if let Err(e) = clock.step(c8) {
+            crate::report_crash(c8, args.speed, e);
+            break;
+        }
+    }
+    out.flush().unwrap();
+}
+
+pub fn run(args: TraceArgs) {
+    let mut c8 = Chip8::builder().quirks(args.quirks).seed(args.seed).build();
+    if let Err(e) = c8.load_rom(&crate::rom::read_or_exit(&args.rom)) {
+        error!("{}: {}", args.rom.display(), e);
+        exit(crate::rom::EXIT_INVALID);
+    }
+
+    if let Some(path) = &args.write {
+        write_trace(&mut c8, &args, path);
+        return;
+    }
+
+    let path = args.reference.as_ref().unwrap();
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        error!("cannot read {}: {}", path.display(), e);
+        exit(1);
+    });
+
+    let mut compared = 0;
+    let mut clock = Clock::new(args.speed);
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let expected = Snapshot::parse(line).unwrap_or_else(|e| {
+            error!("{}:{}: {}", path.display(), i + 1, e);
+            exit(1);
+        });
+
+        let actual = Snapshot::of(&c8);
+        if actual != expected {
+            println!(
+                "diverged at instruction {} (line {} of {})",
+                compared,
+                i + 1,
+                path.display()
+            );
+            expected.print("reference");
+            actual.print("chip8-rs");
+            println!("  last instructions executed:");
+            for (pc, oc) in c8.recent_instructions() {
+                println!("    {:#05x}  {:04x}  {:?}", pc, oc, decode(oc));
+            }
+            exit(1);
+        }
+
+        if let Err(e) = clock.step(&mut c8) {
+            crate::report_crash(&c8, args.speed, e);
+            exit(1);
+        }
+        compared += 1;
+    }
+    println!("{} instructions match the reference", compared);
+}
@@ -0,0 +1,45 @@
+// `verify`: golden image regression checks. Runs a ROM for a fixed number of
+// cycles with a fixed seed and compares the hash of the final screen, so a
+// corpus of ROM, cycle count and hash triples can be replayed after changes.
+use crate::cli::VerifyArgs;
+use crate::headless::run_cycles;
+use chip8_core::Chip8;
+use log::error;
+use std::process::exit;
+
+pub fn run(args: VerifyArgs) {
+    let mut c8 = Chip8::builder().quirks(args.quirks).seed(args.seed).build();
+    if let Err(e) = c8.load_rom(&crate::rom::read_or_exit(&args.rom)) {
+        error!("{}: {}", args.rom.display(), e);
+        exit(crate::rom::EXIT_INVALID);
+    }
+    if let Err(e) = run_cycles(&mut c8, args.cycles, args.speed) {
+        crate::report_crash(&c8, args.speed, e);
+        exit(1);
+    }
+
+    let hash = c8.framebuffer_hash();
+    if args.record_expect {
+        println!(
+            "{} --cycles {} --speed {} --expect {:016x}",
+            args.rom.display(),
+            args.cycles,
+            args.speed,
+            hash
+        );
+        return;
+    }
+
+    let expected = args.expect.unwrap_or_default();
+    if hash == expected {
+        println!("{}: ok", args.rom.display());
+    } else {
+        println!(
+            "{}: screen {:016x} does not match expected {:016x}",
+            args.rom.display(),
+            hash,
+            expected
+        );
+        exit(1);
+    }
+}
@@ -0,0 +1,54 @@
+// `watch`: runs a ROM like `run`, and reloads it whenever the file is saved.
+// With Octo sources (.o8) editing and playing a game is one loop. A source
+// that fails to assemble is reported and the last good build keeps running.
+use crate::octo::Symbols;
+use crate::rom;
+use chip8_core::Chip8;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// how often the file is looked at
+const POLL: Duration = Duration::from_millis(250);
+
+pub struct Watcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Watcher {
+    pub fn new(path: &Path) -> Watcher {
+        Watcher {
+            path: path.to_path_buf(),
+            modified: modified(path),
+            checked: Instant::now(),
+        }
+    }
+
+    /// Whether the file was saved since the last time this returned true.
+    pub fn changed(&mut self) -> bool {
+        if self.checked.elapsed() < POLL {
+            return false;
+        }
+        self.checked = Instant::now();
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+/// The ROM at `path` like `rom::load`, checked to fit in `memory_size` bytes
+/// of memory so a bad save can't stop the emulator.
+pub fn build(path: &Path, memory_size: usize) -> Result<(Vec<u8>, Symbols), String> {
+    let (data, symbols) = rom::load(path).map_err(|e| e.to_string())?;
+    Chip8::check_rom(&data, memory_size).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok((data, symbols))
+}